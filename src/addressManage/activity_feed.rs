@@ -0,0 +1,284 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::env;
+use std::time::Duration;
+
+use crate::error::Error;
+
+/*
+ *  钱包活动数据源
+ *  第三方的钱包活动接口直接给出每个钱包的买卖记录(event_type、token_amount、cost_usd、buy_cost_usd...)，
+ *  load_user_info 通过 ActivityProvider 获取任意地址的活动记录
+ */
+
+/// 获取钱包在一段时间内的活动记录(从新到旧)
+#[async_trait]
+pub trait ActivityProvider: Send + Sync {
+    /// - `since` 只返回 timestamp >= since 的记录
+    /// - `until` 只返回 timestamp <= until 的记录
+    async fn fetch_activities(
+        &self,
+        wallet: &Pubkey,
+        since: Option<u64>,
+        until: Option<u64>,
+    ) -> Result<Vec<Value>, Error>;
+}
+
+/// 活动接口的配置
+#[derive(Debug, Clone)]
+pub struct ActivityFeedConfig {
+    pub base_url: String,        // 活动接口的完整地址
+    pub api_key: Option<String>, // 鉴权的 key，没有时不发送
+    pub api_key_header: String,  // 携带 key 的 header
+    pub page_size: usize,        // 每页的记录数量
+    pub max_pages: usize,        // 单个钱包最多翻的页数
+    pub timeout: Duration,       // 单次请求的超时时间
+}
+
+impl ActivityFeedConfig {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            api_key: None,
+            api_key_header: "x-api-key".to_string(),
+            page_size: 50,
+            max_pages: 20,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// 从环境变量读取配置: ACTIVITY_API_URL(必须)、ACTIVITY_API_KEY、ACTIVITY_API_KEY_HEADER
+    pub fn from_env() -> Option<Self> {
+        let mut config = Self::new(env::var("ACTIVITY_API_URL").ok()?);
+        config.api_key = env::var("ACTIVITY_API_KEY").ok();
+        if let Ok(header) = env::var("ACTIVITY_API_KEY_HEADER") {
+            config.api_key_header = header;
+        }
+        Some(config)
+    }
+}
+
+// 接口的响应: {"code": 0, "msg": "success", "data": {"activities": [...], "next": "..."}}
+#[derive(Debug, Deserialize)]
+struct ActivityResponse {
+    code: i64,
+    #[serde(default)]
+    msg: String,
+    data: Option<ActivityPage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActivityPage {
+    #[serde(default)]
+    activities: Vec<Value>,
+    next: Option<String>, // 下一页的游标，没有更多数据时为空
+}
+
+/// 通过 http 请求活动接口
+pub struct HttpActivityProvider {
+    pub client: reqwest::Client,
+    pub config: ActivityFeedConfig,
+}
+
+impl HttpActivityProvider {
+    pub fn new(config: ActivityFeedConfig) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(|err| Error::ActivityFeedError(format!("{:?}", err)))?;
+        Ok(Self { client, config })
+    }
+
+    // 请求一页数据
+    async fn fetch_page(
+        &self,
+        wallet: &Pubkey,
+        cursor: Option<&str>,
+    ) -> Result<ActivityPage, Error> {
+        let mut query = vec![
+            ("wallet", wallet.to_string()),
+            ("limit", self.config.page_size.to_string()),
+            ("type", "buy".to_string()),
+            ("type", "sell".to_string()),
+        ];
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor.to_string()));
+        }
+        let mut request = self.client.get(&self.config.base_url).query(&query);
+        if let Some(api_key) = &self.config.api_key {
+            request = request.header(self.config.api_key_header.as_str(), api_key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| Error::ActivityFeedError(format!("{:?}", err)))?
+            .json::<ActivityResponse>()
+            .await
+            .map_err(|err| Error::ActivityFeedError(format!("Invalid response: {:?}", err)))?;
+        if response.code != 0 {
+            return Err(Error::ActivityFeedError(format!(
+                "code {}: {}",
+                response.code, response.msg
+            )));
+        }
+        response
+            .data
+            .ok_or_else(|| Error::ActivityFeedError("response without data".to_string()))
+    }
+}
+
+#[async_trait]
+impl ActivityProvider for HttpActivityProvider {
+    // 按游标翻页，直到没有下一页、记录早于 since 或者达到最大页数
+    async fn fetch_activities(
+        &self,
+        wallet: &Pubkey,
+        since: Option<u64>,
+        until: Option<u64>,
+    ) -> Result<Vec<Value>, Error> {
+        let mut activities = vec![];
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..self.config.max_pages {
+            let page = self.fetch_page(wallet, cursor.as_deref()).await?;
+            let (in_window, reached_since) = filter_window(page.activities, since, until);
+            activities.extend(in_window);
+
+            cursor = page.next.filter(|next| !next.is_empty());
+            if reached_since || cursor.is_none() {
+                break;
+            }
+        }
+        Ok(activities)
+    }
+}
+
+/// 固定的活动记录，用于离线分析
+pub struct StaticActivityProvider {
+    pub activities: Vec<Value>,
+}
+
+#[async_trait]
+impl ActivityProvider for StaticActivityProvider {
+    async fn fetch_activities(
+        &self,
+        _wallet: &Pubkey,
+        since: Option<u64>,
+        until: Option<u64>,
+    ) -> Result<Vec<Value>, Error> {
+        Ok(filter_window(self.activities.clone(), since, until).0)
+    }
+}
+
+// 过滤出时间窗口内的记录，返回的 bool 表示是否已经遇到早于 since 的记录
+// 没有 timestamp 的记录无法判断时间，保留下来
+fn filter_window(
+    activities: Vec<Value>,
+    since: Option<u64>,
+    until: Option<u64>,
+) -> (Vec<Value>, bool) {
+    let mut reached_since = false;
+    let activities = activities
+        .into_iter()
+        .filter(|activity| {
+            let Some(timestamp) = activity.get("timestamp").and_then(|v| v.as_u64()) else {
+                return true;
+            };
+            if since.is_some_and(|since| timestamp < since) {
+                reached_since = true;
+                return false;
+            }
+            until.is_none_or(|until| timestamp <= until)
+        })
+        .collect();
+    (activities, reached_since)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addressManage::mock_rpc::{read_request, write_response};
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    // 本地的活动接口替身: 三页数据，游标为页码，校验 api key
+    async fn start_feed_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "http://{}/wallet_activity/sol",
+            listener.local_addr().unwrap()
+        );
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let (head, _) = read_request(&mut stream).await.unwrap();
+                if !head.to_lowercase().contains("x-api-key: secret") {
+                    write_response(&mut stream, "401 Unauthorized", "")
+                        .await
+                        .unwrap();
+                    continue;
+                }
+                let page: u64 = head
+                    .split_once("cursor=")
+                    .and_then(|(_, rest)| rest.split(|c: char| !c.is_ascii_digit()).next())
+                    .and_then(|cursor| cursor.parse().ok())
+                    .unwrap_or(0);
+                // 每页两条记录，时间从新到旧
+                let activities: Vec<Value> = (0..2)
+                    .map(|i| {
+                        let timestamp = 1_000 - (page * 2 + i) * 100;
+                        json!({ "tx_hash": format!("tx{}", timestamp), "timestamp": timestamp })
+                    })
+                    .collect();
+                let next = (page < 2).then(|| (page + 1).to_string());
+                let body = json!({
+                    "code": 0,
+                    "msg": "success",
+                    "data": { "activities": activities, "next": next },
+                });
+                write_response(&mut stream, "200 OK", &body.to_string())
+                    .await
+                    .unwrap();
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_http_activity_provider() {
+        let url = start_feed_server().await;
+        let mut config = ActivityFeedConfig::new(url);
+        config.api_key = Some("secret".to_string());
+        let provider = HttpActivityProvider::new(config.clone()).unwrap();
+        let wallet = Pubkey::new_unique();
+
+        // 翻完所有页: 1000, 900, ..., 500
+        let all = provider
+            .fetch_activities(&wallet, None, None)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 6);
+
+        // 遇到早于 since 的记录之后不再翻页
+        let window = provider
+            .fetch_activities(&wallet, Some(750), Some(900))
+            .await
+            .unwrap();
+        let timestamps: Vec<u64> = window
+            .iter()
+            .map(|activity| activity["timestamp"].as_u64().unwrap())
+            .collect();
+        assert_eq!(timestamps, vec![900, 800]);
+
+        // 缺少 key 时返回错误
+        config.api_key = None;
+        let provider = HttpActivityProvider::new(config).unwrap();
+        assert!(provider
+            .fetch_activities(&wallet, None, None)
+            .await
+            .is_err());
+    }
+}
@@ -1,28 +1,34 @@
-use crate::constant::{PUMP_PROGRAM_ID, RAYDIUM_PROGRAM_ID};
-use crate::error::{Error, TxParseError};
+use crate::addressManage::activity_feed::ActivityProvider;
+use crate::addressManage::fetcher::{
+    is_retryable, retry_with_backoff, FailedSignature, FetchConfig, FetchReport, RateLimiter,
+};
+use crate::addressManage::price_provider::{mark_to_market, PriceProvider};
+use crate::addressManage::registry::WalletRegistry;
+use crate::addressManage::source::{RpcSource, TransactionSource};
+use crate::addressManage::tx_store::{CachePolicy, TransactionStore};
+use crate::error::Error;
+use crate::states::activity::{ActivityValidationReport, RejectedActivity, WalletActivity};
+use crate::states::holding::StyleThresholds;
 use crate::states::states::*;
+use crate::states::window::DEFAULT_WINDOWS;
 use crate::utils::analyze_utils::*;
-use crate::utils::analyze_utils::{get_balance_change, is_target};
-use dotenv::dotenv;
-use reqwest::Client;
+use crate::utils::scoring::Scorer;
+use crate::utils::swap_decoder::DecodedTransaction;
+use futures::{stream, StreamExt};
 use serde::Deserialize;
 use serde_json::{json, Value};
-use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
-use solana_client::rpc_config::{RpcBlockConfig, RpcTransactionConfig};
-use solana_sdk::commitment_config::CommitmentConfig;
+use solana_program::address_lookup_table::state::AddressLookupTable;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
 use solana_transaction_status_client_types::{
-    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiAccountsList,
-    UiTransactionEncoding,
+    EncodedConfirmedTransactionWithStatusMeta, UiTransactionStatusMeta,
 };
 use std::collections::HashMap;
 use std::env;
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::task;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /**
  *  当前模块负责：
@@ -31,36 +37,99 @@ use tokio::task;
  *  3.分析出每个地址在过去一段时间的买入的token数量
  *  4.分析出每个地址在这段时间内的收益(真想跟meme的话，感觉不需要看长线)
  */
-
 pub struct Collector {
-    pub client: RpcClient,
-    pub retry_delay: Duration,
+    pub source: Arc<dyn TransactionSource>, // 签名/交易数据源(rpc 节点或者录制的 fixture)
+    pub retry_delay: Duration,              // 第一次重试前的等待时间，之后指数退避
     pub interval: u64,
+    pub fetch_config: FetchConfig,
+    pub limiter: RateLimiter,            // 当前 rpc 节点的限速器
+    pub store: Option<TransactionStore>, // 本地交易缓存，None 时每次都请求 rpc
 }
 
 impl Collector {
     // 初始化方法
     pub fn new(interval: u64) -> anyhow::Result<Arc<Self>> {
+        Self::with_fetch_config(interval, FetchConfig::default())
+    }
+
+    // 使用自定义的并发/限速/重试配置初始化，节点地址读取 RPC_URL，没有设置时返回错误
+    // 设置了 TX_CACHE_DIR 时使用该目录缓存 finalized 的交易
+    pub fn with_fetch_config(
+        interval: u64,
+        fetch_config: FetchConfig,
+    ) -> anyhow::Result<Arc<Self>> {
+        let rpc_url =
+            env::var("RPC_URL").map_err(|err| anyhow::anyhow!("RPC_URL is not set: {}", err))?;
+        let store = match env::var("TX_CACHE_DIR") {
+            Ok(dir) => Some(TransactionStore::open(dir, CachePolicy::Finalized)?),
+            Err(_) => None,
+        };
+        Self::with_store(interval, rpc_url, fetch_config, store)
+    }
+
+    // 使用指定的节点地址与交易缓存初始化
+    pub fn with_store(
+        interval: u64,
+        rpc_url: String,
+        fetch_config: FetchConfig,
+        store: Option<TransactionStore>,
+    ) -> anyhow::Result<Arc<Self>> {
+        let source = RpcSource::new(rpc_url);
+        Self::with_source(interval, fetch_config, Arc::new(source), store)
+    }
+
+    // 使用指定的数据源初始化，比如离线分析时使用 FixtureSource
+    pub fn with_source(
+        interval: u64,
+        fetch_config: FetchConfig,
+        source: Arc<dyn TransactionSource>,
+        store: Option<TransactionStore>,
+    ) -> anyhow::Result<Arc<Self>> {
         let rc_self = Arc::new(Self {
-            client: RpcClient::new(env::var("RPC_URL").expect("RPC_URL is not set")),
+            source,
             retry_delay: Duration::new(1, 0), //1秒重试
             interval,
+            limiter: RateLimiter::new(fetch_config.requests_per_second),
+            fetch_config,
+            store,
         });
         Ok(rc_self)
     }
 
-    pub async fn get_history_tx(&self, user: &mut User, limit: usize) -> Result<(), Error> {
-        // 配置查询参数
-        let config = GetConfirmedSignaturesForAddress2Config {
-            before: None,
-            until: None,
-            limit: Some(limit),
-            commitment: Some(CommitmentConfig::confirmed()),
-        };
-        // 调用 solana_client 提供的官方方法
-        let statuses = self
-            .client
-            .get_signatures_for_address_with_config(&user.address, config)
+    /// 分页获取用户在 `time_day` 天内的交易签名(从新到旧)
+    /// - `limit` 最多获取的签名数量(硬上限)
+    /// - `until` 上一次看到的最新签名，传入时只获取比它更新的交易，用于增量更新
+    /// - 每页请求 `FetchConfig::signatures_per_page` 个签名，用 `before` 游标向前翻页，
+    ///   直到最旧交易的 block_time 超出时间窗口、没有更多交易或者达到上限
+    /// - `time_day` 为 0 时不限制时间，只受 `limit` 限制
+    pub async fn get_history_tx(
+        &self,
+        user: &mut User,
+        limit: usize,
+        until: Option<Signature>,
+    ) -> Result<(), Error> {
+        let cutoff = history_cutoff(user.time_day, unix_timestamp());
+        let mut signatures: Vec<String> = vec![];
+        let mut before = None;
+        // 每页的签名数量限制在 1 到节点上限之间
+        let per_page = self
+            .fetch_config
+            .signatures_per_page
+            .clamp(1, MAX_SIGNATURES_PER_PAGE);
+
+        while signatures.len() < limit {
+            let page_size = (limit - signatures.len()).min(per_page);
+            // 数据源按 getSignaturesForAddress 的语义分页
+            let statuses = retry_with_backoff(
+                &self.fetch_config,
+                &self.limiter,
+                self.retry_delay,
+                is_retryable,
+                || {
+                    self.source
+                        .get_signatures(&user.address, before, until, page_size)
+                },
+            )
             .await
             .map_err(|err| {
                 Error::GetHistoryTxError(format!(
@@ -69,52 +138,317 @@ impl Collector {
                 ))
             })?;
 
-        // 提取 signature 字段
-        let signatures: Vec<String> = statuses.into_iter().map(|s| s.signature).collect();
+            let page_len = statuses.len();
+            let (page, reached_cutoff) = take_in_window(
+                statuses.into_iter().map(|s| (s.signature, s.block_time)),
+                cutoff,
+            );
+            before = match page.last() {
+                Some(last) => Some(Signature::from_str(last).map_err(|err| {
+                    Error::GetHistoryTxError(format!("Invalid signature {}: {:?}", last, err))
+                })?),
+                None => None,
+            };
+            signatures.extend(page);
+
+            // 超出时间窗口或者已经是最后一页
+            if reached_cutoff || page_len < page_size || before.is_none() {
+                break;
+            }
+        }
 
         user.history_txs = signatures;
         Ok(())
     }
 
     // 分析当前传入的交易中，与代币相关的交易(pump.fun、raydium)
-    pub async fn get_token_txs(&self, user: &mut User) -> Result<(), Error> {
-        let txs = user.clone().history_txs;
-
-        // 使用getTranscation的rpc方法获取交易的具体信息
-        for tx in txs.iter() {
-            let signature = Signature::from_str(tx).unwrap();
-            let config = RpcTransactionConfig {
-                encoding: Some(UiTransactionEncoding::Base58),
-                commitment: Some(CommitmentConfig::confirmed()),
-                max_supported_transaction_version: Some(0),
+    // 交易按并发上限同时获取，单个交易获取失败只记录在返回的 FetchReport 中，不影响其他交易
+    pub async fn get_token_txs(&self, user: &mut User) -> Result<FetchReport, Error> {
+        let txs = user.history_txs.clone();
+        let mut report = FetchReport::default();
+        let finalized_slot = self.cache_finalized_slot(&txs).await;
+        let mut events = vec![];
+
+        // buffered 保持交易原来的顺序
+        let mut results = stream::iter(txs.into_iter().map(|tx| async move {
+            let decoded = self.fetch_transaction(&tx, finalized_slot).await;
+            (tx, decoded)
+        }))
+        .buffered(self.fetch_config.concurrency.max(1));
+
+        while let Some((tx, decoded)) = results.next().await {
+            let decoded = match decoded {
+                Ok(decoded) => decoded,
+                Err(error) => {
+                    report.failed.push(FailedSignature {
+                        signature: tx,
+                        error,
+                    });
+                    continue;
+                }
             };
-            // 直接阻塞调用
-            let transaction_result = self
-                .client
-                .get_transaction_with_config(&signature, config)
-                .await
-                .map_err(|err| Error::GetHistoryTxError(format!("Failed: {:?}", err)))?;
-
-            // 当前交易的所有account
-            let Vtx = transaction_result.transaction.transaction.decode().unwrap();
-            let accounts = Vtx.message.static_account_keys();
-
-            // 如果交易中的accounts中存在pump.fun和raydium我们就认为这个交易是买卖代币的交易
-            let result = is_target(accounts);
+            report.fetched += 1;
+            user.latest_slot = user.latest_slot.max(decoded.slot);
+
+            // 如果交易中调用了pump.fun和raydium我们就认为这个交易是买卖代币的交易
+            let result = decoded.is_target();
             if result {
-                // 如果是买卖代币的交易的话，我想想能不能将具体买卖代币的种类与时间给分析出来
-                user.token_txs.push(tx.clone());
+                // 解析出具体买卖的代币与数量
+                events.extend(decoded.swap_events(&user.address));
+                // 拆分 sol 变化，盈亏按 swap 本身计算，手续费等成本单独记录
+                if let Some((sol_change, _)) = decoded.balance_change(&user.address, &[]) {
+                    user.record_sol_change(&sol_change);
+                }
+                user.token_txs.push(tx);
             } else {
                 continue;
             }
         }
-        Ok(())
+        // 签名是从新到旧的，买卖按时间顺序统计才能正确扣除持仓成本
+        user.record_swap_events(events);
+        Ok(report)
+    }
+
+    /// 从地址库读取一批钱包，获取交易并评分之后把分析时间、最新的 slot 与评分写回地址库
+    /// - `batch_size` 本批次分析的钱包数量
+    /// - `limit` 每个钱包最多获取的签名数量
+    /// - `time_day` 分析的时间窗口(天)
+    /// - `scorer` 评分器，一般由 `ScoringConfig` 构建
+    /// - `price_provider` 给还没卖出的持仓估值，未实现盈亏计入余额变化与评分
+    /// - 签名、任意一笔交易获取失败或者持仓估值失败的钱包记入 `failed`，不写回，
+    ///   分析时间保持不变，下一批次会优先再分析
+    pub async fn analyze_registry_batch(
+        &self,
+        registry: &WalletRegistry,
+        batch_size: usize,
+        limit: usize,
+        time_day: u8,
+        scorer: &dyn Scorer,
+        price_provider: &dyn PriceProvider,
+    ) -> Result<BatchReport, Error> {
+        let mut report = BatchReport::default();
+        for address in get_address(registry, batch_size)? {
+            let mut user = User::new(address, time_day);
+            if let Err(error) = self.get_history_tx(&mut user, limit, None).await {
+                report.failed.push(FailedWallet { address, error });
+                continue;
+            }
+            let fetch_report = match self.get_token_txs(&mut user).await {
+                Ok(fetch_report) => fetch_report,
+                Err(error) => {
+                    report.failed.push(FailedWallet { address, error });
+                    continue;
+                }
+            };
+            // 缺少交易时统计出来的盈亏不完整，不能当作分析完成
+            if let Some(first) = fetch_report.failed.first() {
+                let error = Error::GetTokenTxError(format!(
+                    "{} of {} transactions failed, first {}: {:?}",
+                    fetch_report.failed.len(),
+                    fetch_report.failed.len() + fetch_report.fetched,
+                    first.signature,
+                    first.error
+                ));
+                report.failed.push(FailedWallet { address, error });
+                continue;
+            }
+            // 同样，持仓估值失败时评分不完整
+            if let Err(error) = mark_to_market(&mut user, price_provider).await {
+                report.failed.push(FailedWallet { address, error });
+                continue;
+            }
+            user.count_balance_change();
+
+            let now = unix_timestamp().max(0) as u64;
+            user.classify_style(&StyleThresholds::default(), now);
+            user.compute_metrics();
+            user.compute_windows(&DEFAULT_WINDOWS, now);
+            user.score_with(scorer);
+
+            let slot = (user.latest_slot > 0).then_some(user.latest_slot);
+            registry.record_analysis(&address, slot, user.score, now)?;
+            report.analyzed.push(user);
+        }
+        Ok(report)
+    }
+
+    // 按 Finalized 策略写缓存时需要最新的 finalized slot，全部命中缓存或者获取失败时为 None
+    pub(crate) async fn cache_finalized_slot(&self, txs: &[String]) -> Option<u64> {
+        let store = self.store.as_ref()?;
+        if store.policy != CachePolicy::Finalized || txs.iter().all(|tx| store.contains(tx)) {
+            return None;
+        }
+        retry_with_backoff(
+            &self.fetch_config,
+            &self.limiter,
+            self.retry_delay,
+            is_retryable,
+            || self.source.get_finalized_slot(),
+        )
+        .await
+        .ok()
+    }
+
+    // 获取交易的原始数据，先查本地缓存，没有时从数据源获取并按缓存策略写入缓存
+    pub async fn fetch_encoded_transaction(
+        &self,
+        tx: &str,
+        finalized_slot: Option<u64>,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, Error> {
+        if let Some(cached) = self.store.as_ref().and_then(|store| store.get(tx)) {
+            return Ok(cached);
+        }
+
+        let signature = Signature::from_str(tx).map_err(|err| {
+            Error::GetTokenTxError(format!("Invalid signature {}: {:?}", tx, err))
+        })?;
+        let transaction_result = retry_with_backoff(
+            &self.fetch_config,
+            &self.limiter,
+            self.retry_delay,
+            is_retryable,
+            || self.source.get_transaction(&signature),
+        )
+        .await
+        .map_err(|err| Error::GetHistoryTxError(format!("Failed: {:?}", err)))?;
+
+        // 缓存写入失败不影响本次分析
+        if let Some(store) = &self.store {
+            let _ = store.put(tx, &transaction_result, finalized_slot);
+        }
+        Ok(transaction_result)
+    }
+
+    // 获取交易的具体信息，并展开顶层指令与内部指令
+    pub async fn fetch_transaction(
+        &self,
+        tx: &str,
+        finalized_slot: Option<u64>,
+    ) -> Result<DecodedTransaction, Error> {
+        let transaction_result = self.fetch_encoded_transaction(tx, finalized_slot).await?;
+
+        // 当前交易的所有account(包括地址查找表加载的account)
+        let vtx = transaction_result
+            .transaction
+            .transaction
+            .decode()
+            .ok_or_else(|| {
+                Error::GetTokenTxError(format!("Failed to decode transaction {}", tx))
+            })?;
+        let meta = transaction_result.transaction.meta;
+        let accounts = self.resolve_account_keys(&vtx, meta.as_ref()).await?;
+
+        // 聚合器/bot 通过 CPI 调用的 pump.fun、raydium 也能被识别
+        Ok(DecodedTransaction::new(
+            tx.to_string(),
+            transaction_result.slot,
+            transaction_result.block_time,
+            &vtx,
+            accounts,
+            meta,
+        ))
+    }
+
+    // 获取交易完整的账户列表，meta 中没有 loaded_addresses 时从链上读取地址查找表
+    pub async fn resolve_account_keys(
+        &self,
+        tx: &VersionedTransaction,
+        meta: Option<&UiTransactionStatusMeta>,
+    ) -> Result<Vec<Pubkey>, Error> {
+        if let Some(account_keys) = get_full_account_keys(tx, meta) {
+            return Ok(account_keys);
+        }
+
+        let lookups = tx.message.address_table_lookups().unwrap_or_default();
+        let mut tables = HashMap::new();
+        for lookup in lookups {
+            let data = retry_with_backoff(
+                &self.fetch_config,
+                &self.limiter,
+                self.retry_delay,
+                is_retryable,
+                || self.source.get_account_data(&lookup.account_key),
+            )
+            .await
+            .map_err(|err| {
+                Error::ResolveAccountKeysError(format!(
+                    "Failed to fetch lookup table {}: {:?}",
+                    lookup.account_key, err
+                ))
+            })?;
+            let table = AddressLookupTable::deserialize(&data).map_err(|err| {
+                Error::ResolveAccountKeysError(format!(
+                    "Invalid lookup table {}: {:?}",
+                    lookup.account_key, err
+                ))
+            })?;
+            tables.insert(lookup.account_key, table.addresses.to_vec());
+        }
+
+        let loaded = resolve_lookup_addresses(lookups, &tables).ok_or_else(|| {
+            Error::ResolveAccountKeysError("lookup table index out of range".to_string())
+        })?;
+        let mut account_keys = tx.message.static_account_keys().to_vec();
+        account_keys.extend(loaded);
+        Ok(account_keys)
+    }
+}
+
+// getSignaturesForAddress 每页最多返回的签名数量
+const MAX_SIGNATURES_PER_PAGE: usize = 1000;
+
+// 当前的 unix 时间戳(秒)
+fn unix_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// 时间窗口的起点，`time_day` 为 0 时不限制
+fn history_cutoff(time_day: u8, now: i64) -> Option<i64> {
+    (time_day > 0).then(|| now - time_day as i64 * 24 * 60 * 60)
+}
+
+// 取出一页签名中在时间窗口内的部分，返回的 bool 表示是否已经遇到窗口之外的交易
+// 没有 block_time 的交易无法判断时间，保留下来
+fn take_in_window(
+    statuses: impl IntoIterator<Item = (String, Option<i64>)>,
+    cutoff: Option<i64>,
+) -> (Vec<String>, bool) {
+    let mut signatures = vec![];
+    for (signature, block_time) in statuses {
+        if let (Some(cutoff), Some(block_time)) = (cutoff, block_time) {
+            if block_time < cutoff {
+                return (signatures, true);
+            }
+        }
+        signatures.push(signature);
     }
+    (signatures, false)
+}
+
+/// 从地址库中获取下一批需要分析的钱包地址
+pub fn get_address(registry: &WalletRegistry, limit: usize) -> Result<Vec<Pubkey>, Error> {
+    Ok(registry
+        .next_batch(limit)?
+        .into_iter()
+        .map(|record| record.address)
+        .collect())
 }
 
-// 连接数据库，获取地址信息
-pub fn get_address() -> String {
-    "address".to_string()
+/// 分析失败的钱包
+#[derive(Debug)]
+pub struct FailedWallet {
+    pub address: Pubkey,
+    pub error: Error,
+}
+
+/// 一批钱包的分析结果
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub analyzed: Vec<User>,       // 分析完成并写回地址库的钱包
+    pub failed: Vec<FailedWallet>, // 分析失败的钱包，没有写回
 }
 
 pub fn get_default_address() -> Pubkey {
@@ -122,13 +456,14 @@ pub fn get_default_address() -> Pubkey {
 }
 
 #[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct TransactionSignature {
-    pub blockTime: Option<u64>,             // 时间戳
-    pub confirmationStatus: Option<String>, // 确认状态
-    pub err: Option<String>,                // 错误
-    pub memo: Option<String>,               // 备注
-    pub signature: String,                  // 交易签名
-    pub slot: u64,                          // Slot
+    pub block_time: Option<u64>,             // 时间戳
+    pub confirmation_status: Option<String>, // 确认状态
+    pub err: Option<String>,                 // 错误
+    pub memo: Option<String>,                // 备注
+    pub signature: String,                   // 交易签名
+    pub slot: u64,                           // Slot
 }
 
 #[derive(Deserialize, Debug)]
@@ -230,120 +565,78 @@ pub fn get_default_user_activities() -> Vec<Value> {
 }
 
 // 主函数：加载用户信息
-pub fn load_user_info(user: &mut User) -> Result<(), TxParseError> {
-    let activities = get_default_user_activities();
-    let user_token_stats = &mut user.token_stats;
-
-    for activity in activities.iter() {
-        // 每条记录的利润先默认为0
-        let mut tx_profit = 0.0;
-        // 成本
-        let mut cost = 0.0;
-
-        // 1. 获取并处理 token 信息
-        if let Some(token_info) = activity.get("token") {
-            if let Some(address_str) = token_info.get("address").and_then(|v| v.as_str()) {
-                let mint = string_to_pub_key(address_str);
-
-                // 判断是否已存在
-                if let Some(trade_stats) = user_token_stats.get_mut(&mint) {
-                    // 已存在 => 更新
-                    tx_profit = handle_event_type(token_info, trade_stats)?;
-                } else {
-                    // 不存在 => 初始化
-                    let mut token_status = TokenTradeStats::new(mint);
-                    // 读取 symbol
-                    if let Some(symbol) = token_info.get("symbol").and_then(|v| v.as_str()) {
-                        token_status.symbol = symbol.to_string();
-                    }
-                    user.distinct_token_count += 1;
-
-                    // 处理 buy/sell
-                    let event_type = activity.get("event_type").and_then(|v| v.as_str());
-                    // 买入统计成本，卖出统计利润
-                    match event_type {
-                        Some("buy") => {
-                            cost = handle_event_type(activity, &mut token_status)?;
-                        }
-                        Some("sell") => {
-                            tx_profit = handle_event_type(activity, &mut token_status)?;
-                        }
-                        _ => {}
-                    }
-                    // 插入 map
-                    user_token_stats.insert(mint, token_status);
-                }
+// 从活动数据源获取用户在 `time_day` 天内的买卖记录，返回每条记录的校验结果
+pub async fn load_user_info(
+    user: &mut User,
+    provider: &dyn ActivityProvider,
+) -> Result<ActivityValidationReport, Error> {
+    let since = user.window_start(unix_timestamp().max(0) as u64);
+    let activities = provider
+        .fetch_activities(&user.address, since, None)
+        .await?;
+    Ok(apply_activities(user, &activities, since))
+}
+
+// 把活动记录统计到用户的买卖信息中，没有通过校验的记录收集到报告里
+// 数据源不一定支持按时间过滤，早于 `since` 的买卖在这里丢掉
+pub fn apply_activities(
+    user: &mut User,
+    activities: &[Value],
+    since: Option<u64>,
+) -> ActivityValidationReport {
+    let mut report = ActivityValidationReport {
+        total: activities.len(),
+        ..Default::default()
+    };
+    let mut events = vec![];
+    let mut symbols = HashMap::new();
+
+    for (index, value) in activities.iter().enumerate() {
+        let parsed = WalletActivity::from_value(value).and_then(|activity| {
+            let event = activity.to_swap_event(&user.address)?;
+            Ok((activity, event))
+        });
+        let (activity, event) = match parsed {
+            Ok((activity, Some(event))) => (activity, event),
+            Ok((_, None)) => {
+                report.skipped += 1;
+                continue;
+            }
+            Err(error) => {
+                report.rejected.push(RejectedActivity {
+                    index,
+                    tx_hash: value
+                        .get("tx_hash")
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string),
+                    error,
+                });
+                continue;
             }
+        };
+        if since.is_some_and(|since| event.block_time < since) {
+            report.out_of_window += 1;
+            continue;
         }
+        report.accepted += 1;
 
-        // 2. 处理 tx_hash
-        if let Some(tx_sig) = activity.get("tx_hash").and_then(|v| v.as_str()) {
-            user.token_txs.push(tx_sig.to_string());
+        // 1. 收集 swap 事件与 symbol，数据源从新到旧返回，统一按时间顺序统计
+        if let Some(symbol) = activity.token.symbol {
+            symbols.insert(event.mint, symbol);
         }
+        events.push(event);
 
-        // 3. 更新用户总利润/成本
-        user.total_profit += tx_profit;
-        user.total_cost += cost;
+        // 2. 处理 tx_hash
+        user.token_txs.push(activity.tx_hash);
     }
-    Ok(())
-}
-
-/// 处理 "event_type" => buy/sell 并返回本次交易利润
-fn handle_event_type(
-    activity_info: &Value,
-    stats: &mut TokenTradeStats,
-) -> Result<f64, TxParseError> {
-    let event_type = activity_info.get("event_type").and_then(|v| v.as_str());
-    let timestamp = activity_info
-        .get("timestamp")
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0); // 或者用ok_or(...)?
-
-    // 读取 token_amount
-    let token_amount = activity_info
-        .get("token_amount")
-        .and_then(|v| v.as_f64())
-        .unwrap_or(0.0);
-
-    match event_type {
-        // 买入统计成本，卖出统计利润
-        Some("buy") => {
-            let buy_cost = parse_f64_from_string_field(activity_info, "cost_usd")?;
-            stats.record_buy(token_amount, timestamp);
-            Ok(buy_cost) // 买入不产生利润
-        }
-        Some("sell") => {
-            let buy_cost = parse_f64_from_string_field(activity_info, "buy_cost_usd")?;
-            let sell_cost = parse_f64_from_string_field(activity_info, "cost_usd")?;
-
-            let profit = sell_cost - buy_cost;
-            stats.record_sell(token_amount, timestamp, profit);
-
-            // 判断盈亏记录
-            if profit > 0.0 {
-                stats.win_count += 1;
-            } else if profit < 0.0 {
-                stats.lose_count += 1;
-            }
-            stats.profit += profit;
 
-            Ok(profit)
-        }
-        _ => {
-            // 未知的 or 无 event_type，不做处理
-            Ok(0.0)
+    user.record_swap_events(events);
+    for (mint, symbol) in symbols {
+        if let Some(stats) = user.token_stats.get_mut(&mint) {
+            stats.symbol = symbol;
         }
     }
-}
-
-fn parse_f64_from_string_field(
-    data: &serde_json::Value,
-    field_name: &str,
-) -> Result<f64, TxParseError> {
-    data.get(field_name)
-        .and_then(|v| v.as_str()) // 先尝试获取字符串
-        .and_then(|s| s.parse::<f64>().ok()) // 再尝试解析为 f64
-        .ok_or_else(|| TxParseError::InvalidField(field_name.to_string())) // 如果失败，返回自定义错误
+    report
 }
 
 //
@@ -354,43 +647,309 @@ pub fn analysis() -> Result<(), Error> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::result;
+    use crate::addressManage::activity_feed::StaticActivityProvider;
+    use crate::addressManage::mock_rpc::{Fault, MockRpcServer};
+    use crate::addressManage::price_provider::FixturePriceProvider;
+    use crate::addressManage::source::FixtureSource;
+    use crate::states::swap_event::TradeSide;
+    use crate::utils::scoring::WeightedScorer;
+    use dotenv::dotenv;
+    use std::path::{Path, PathBuf};
+
+    // 使用本地替身节点的 Collector，走真实的 RpcClient 调用路径
+    async fn mock_collector(fetch_config: FetchConfig) -> (MockRpcServer, Arc<Collector>) {
+        mock_collector_in(fixture_dir(), fetch_config).await
+    }
+
+    async fn mock_collector_in(
+        dir: PathBuf,
+        fetch_config: FetchConfig,
+    ) -> (MockRpcServer, Arc<Collector>) {
+        let server = MockRpcServer::start(dir).await.unwrap();
+        let source = Arc::new(RpcSource::new(server.url.clone()));
+        let collector = Collector::with_source(11, fetch_config, source, None).unwrap();
+        (server, collector)
+    }
+
+    fn fixture_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/rpc")
+    }
 
     #[tokio::test]
     async fn test_get_history_tx2() {
-        dotenv().ok();
-        let collector = Collector::new(11).unwrap();
+        let (server, collector) = mock_collector(FetchConfig::default()).await;
         let address = get_default_address();
-        let mut user = User::new(address, 15);
-        let limit: usize = 5;
-        let sigs = collector.get_history_tx(&mut user, limit).await.unwrap();
-        println!("sigs: {:?}", sigs);
+        // 录制的交易在很久以前，不限制时间
+        let mut user = User::new(address, 0);
+        let limit: usize = 2;
+        collector
+            .get_history_tx(&mut user, limit, None)
+            .await
+            .unwrap();
+        assert_eq!(user.history_txs.len(), 2);
+        // 一页就能取完
+        assert_eq!(server.request_count("getSignaturesForAddress"), 1);
+
+        // 每页 1 个签名时翻两页，结果与一页取完相同
+        let fetch_config = FetchConfig {
+            signatures_per_page: 1,
+            ..FetchConfig::default()
+        };
+        let (server, collector) = mock_collector(fetch_config).await;
+        let mut paged = User::new(address, 0);
+        collector
+            .get_history_tx(&mut paged, limit, None)
+            .await
+            .unwrap();
+        assert_eq!(paged.history_txs, user.history_txs);
+        assert_eq!(server.request_count("getSignaturesForAddress"), 2);
     }
 
     #[tokio::test]
     async fn test_get_token_txs() {
-        dotenv().ok();
-        let collector = Collector::new(11).unwrap();
+        let (server, collector) = mock_collector(FetchConfig::default()).await;
         let address = get_default_address();
-        let mut user = User::new(address, 15);
+        let mut user = User::new(address, 0);
         let limit: usize = 5;
-        let sigs = collector.get_history_tx(&mut user, limit).await.unwrap();
-        println!("sigs: {:?}", sigs);
-        let result = collector.get_token_txs(&mut user).await.unwrap();
-        println!("user : {:?}", user);
+        collector
+            .get_history_tx(&mut user, limit, None)
+            .await
+            .unwrap();
+        let report = collector.get_token_txs(&mut user).await.unwrap();
+        assert_eq!(report.fetched, 3);
+        assert_eq!(user.token_txs.len(), 2);
+        assert_eq!(user.sol_pnl, 500_000_000);
+        assert_eq!(server.request_count("getTransaction"), 3);
+    }
+
+    #[tokio::test]
+    async fn test_get_token_txs_with_faults() {
+        let fetch_config = FetchConfig {
+            concurrency: 1,
+            ..FetchConfig::default()
+        };
+        let (server, collector) = mock_collector(fetch_config).await;
+        let mut user = User::new(get_default_address(), 0);
+        collector.get_history_tx(&mut user, 5, None).await.unwrap();
+
+        // 429 由 RpcClient 自己重试(is_retryable 的 429 分支见 fetcher 的测试)，节点暂时不可用由我们重试，
+        // 参数错误直接记为失败，不影响其他交易
+        server.inject("getTransaction", Fault::RateLimited);
+        server.inject(
+            "getTransaction",
+            Fault::Error {
+                code: -32005,
+                message: "Node is unhealthy".to_string(),
+            },
+        );
+        server.inject("getTransaction", Fault::Delay(Duration::from_millis(50)));
+        server.inject(
+            "getTransaction",
+            Fault::Error {
+                code: -32602,
+                message: "Invalid params".to_string(),
+            },
+        );
+        let report = collector.get_token_txs(&mut user).await.unwrap();
+        assert_eq!(report.fetched, 2);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].signature, user.history_txs[1]);
+        assert_eq!(server.request_count("getTransaction"), 5);
+    }
+
+    #[tokio::test]
+    async fn test_collector_with_fixture_source() {
+        let source = Arc::new(FixtureSource::new(fixture_dir()));
+        let collector = Collector::with_source(11, FetchConfig::default(), source, None).unwrap();
+        let address = get_default_address();
+
+        // time_day 为 0 不限制时间，录制的交易都在很久以前
+        let mut user = User::new(address, 0);
+        collector
+            .get_history_tx(&mut user, 100, None)
+            .await
+            .unwrap();
+        assert_eq!(user.history_txs.len(), 3);
+
+        let report = collector.get_token_txs(&mut user).await.unwrap();
+        assert_eq!(report.fetched, 3);
+        assert!(report.failed.is_empty());
+        // 中间的 sol 转账不是买卖代币的交易
+        assert_eq!(user.token_txs.len(), 2);
+        assert_eq!(user.swap_events.len(), 2);
+        // 买卖按时间顺序统计
+        assert_eq!(user.swap_events[0].side, TradeSide::Buy);
+        assert_eq!(user.swap_events[0].token_amount.ui_amount(), 2_000_000.0);
+        assert_eq!(user.swap_events[1].side, TradeSide::Sell);
+        assert_eq!(user.swap_events[1].quote_amount.amount, 2_000_000_000);
+        // 卖出一半，成本为买入的一半: 2 - 1.5 / 2
+        assert_eq!(user.total_cost, 0.75);
+        assert_eq!(user.total_profit, 1.25);
+        let mint = user.swap_events[0].mint;
+        assert_eq!(user.token_stats[&mint].win_count, 1);
+        assert_eq!(user.sol_pnl, 500_000_000);
+        assert_eq!(user.trade_costs.network_fee, 10_000);
+
+        // 从最新的签名开始增量获取，没有新的交易
+        let latest = Signature::from_str(&user.history_txs[0]).unwrap();
+        collector
+            .get_history_tx(&mut user, 100, Some(latest))
+            .await
+            .unwrap();
+        assert!(user.history_txs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_registry_batch() {
+        let (server, collector) = mock_collector(FetchConfig::default()).await;
+        let registry = WalletRegistry::open_in_memory().unwrap();
+        let address = get_default_address();
+        // 没有录制交易的钱包
+        let idle = Pubkey::new_unique();
+        registry
+            .add_candidate(&address, "manual", 100, &[])
+            .unwrap();
+        registry.add_candidate(&idle, "manual", 200, &[]).unwrap();
+        let scorer = WeightedScorer::default();
+        let prices = FixturePriceProvider::load(fixture_dir().join("../prices.json")).unwrap();
+
+        // 有一笔交易获取失败，钱包记为失败，不写回
+        server.inject(
+            "getTransaction",
+            Fault::Error {
+                code: -32602,
+                message: "Invalid params".to_string(),
+            },
+        );
+        let report = collector
+            .analyze_registry_batch(&registry, 10, 100, 0, &scorer, &prices)
+            .await
+            .unwrap();
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].address, address);
+        assert_eq!(report.analyzed.len(), 1);
+        let record = registry.get(&address).unwrap().unwrap();
+        assert_eq!((record.last_analyzed_at, record.score), (None, None));
+
+        // 没有交易的钱包同样记录分析时间，不会一直占用批次
+        let record = registry.get(&idle).unwrap().unwrap();
+        assert_eq!(record.last_analyzed_slot, None);
+        assert!(record.last_analyzed_at.is_some());
+        assert_eq!(record.score, Some(0.0));
+        assert_eq!(get_address(&registry, 1).unwrap(), vec![address]);
+
+        let report = collector
+            .analyze_registry_batch(&registry, 1, 100, 0, &scorer, &prices)
+            .await
+            .unwrap();
+        assert!(report.failed.is_empty());
+        let user = &report.analyzed[0];
+        let record = registry.get(&address).unwrap().unwrap();
+        assert_eq!(record.last_analyzed_slot, Some(314_000_500));
+        assert_eq!(record.score, Some(user.score));
+        assert!(user.score > 0.0);
+        // 剩余持仓按 fixture 价格估值，计入余额变化
+        assert!(user.unpriced_mints.is_empty());
+        assert_eq!(user.unrealized_pnl, -0.25);
+        assert_eq!(user.balance_change, (1.25 - 0.25) / 1.5);
+    }
+
+    #[tokio::test]
+    async fn test_get_history_tx_pages() {
+        // 最近 3 天每天一笔交易，外加一笔 10 天前的交易
+        let dir = std::env::temp_dir().join(format!("history_pages_test_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("signatures")).unwrap();
+        let address = Pubkey::new_unique();
+        let now = unix_timestamp();
+        let signatures: Vec<Signature> = (1..=4u8).map(|i| Signature::from([i; 64])).collect();
+        let statuses: Vec<Value> = signatures
+            .iter()
+            .zip([1, 2, 3, 10])
+            .map(|(signature, days)| {
+                json!({
+                    "signature": signature.to_string(),
+                    "slot": 1_000 - days,
+                    "err": null,
+                    "memo": null,
+                    "blockTime": now - days * 24 * 60 * 60,
+                    "confirmationStatus": "finalized",
+                })
+            })
+            .collect();
+        std::fs::write(
+            dir.join("signatures").join(format!("{}.json", address)),
+            serde_json::to_vec(&statuses).unwrap(),
+        )
+        .unwrap();
+
+        let fetch_config = FetchConfig {
+            signatures_per_page: 1,
+            ..FetchConfig::default()
+        };
+        let (server, collector) = mock_collector_in(dir.clone(), fetch_config).await;
+        let mut user = User::new(address, 7);
+        collector.get_history_tx(&mut user, 10, None).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // 第 4 页的交易超出 7 天的窗口，翻页停止
+        let expected: Vec<String> = signatures[..3].iter().map(|s| s.to_string()).collect();
+        assert_eq!(user.history_txs, expected);
+        // 每页 1 个签名，后一页的 before 是前一页最后的签名
+        let requests = server.request_params("getSignaturesForAddress");
+        assert_eq!(requests.len(), 4);
+        for (page, params) in requests.iter().enumerate() {
+            assert_eq!(params[1]["limit"], 1);
+            let before = page.checked_sub(1).map(|last| signatures[last].to_string());
+            assert_eq!(params[1]["before"].as_str(), before.as_deref());
+        }
     }
 
     #[test]
-    pub fn test_load_user_info() {
+    fn test_take_in_window() {
+        let now = 1_736_812_854;
+        let cutoff = history_cutoff(7, now);
+        assert_eq!(cutoff, Some(now - 7 * 86400));
+        assert_eq!(history_cutoff(0, now), None);
+
+        let page = vec![
+            ("a".to_string(), Some(now - 10)),
+            ("b".to_string(), None),
+            ("c".to_string(), Some(now - 6 * 86400)),
+            ("d".to_string(), Some(now - 8 * 86400)),
+            ("e".to_string(), Some(now - 9 * 86400)),
+        ];
+        let (signatures, reached_cutoff) = take_in_window(page.clone(), cutoff);
+        assert_eq!(signatures, vec!["a", "b", "c"]);
+        assert!(reached_cutoff);
+
+        let (signatures, reached_cutoff) = take_in_window(page, None);
+        assert_eq!(signatures.len(), 5);
+        assert!(!reached_cutoff);
+    }
+
+    #[tokio::test]
+    async fn test_load_user_info() {
         dotenv().ok();
         let address = get_default_address();
-        let mut user = User::new(address, 15);
-        let result = load_user_info(&mut user).unwrap();
+        // 默认的活动记录在很久以前，不限制时间
+        let mut user = User::new(address, 0);
+        let provider = StaticActivityProvider {
+            activities: get_default_user_activities(),
+        };
+        let report = load_user_info(&mut user, &provider).await.unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.accepted, 3);
+        assert_eq!(user.token_txs.len(), 3);
         // println!("user : {}", user);
-        let mut filter_addresses = vec![];
-        filter_addresses.push(string_to_pub_key(
+        let filter_addresses = vec![string_to_pub_key(
             "6xmiC8Gsp6i8owu3JMDpt38vsCGznCmW5Fzjuomqpump",
-        ));
+        )];
         user.display_with_filter(&filter_addresses);
+
+        // 数据源没有按时间过滤时，早于时间窗口的记录不参与统计
+        let mut user = User::new(address, 7);
+        let report = apply_activities(&mut user, &get_default_user_activities(), Some(u64::MAX));
+        assert_eq!((report.accepted, report.out_of_window), (0, 3));
+        assert!(user.swap_events.is_empty() && user.token_txs.is_empty());
     }
 }
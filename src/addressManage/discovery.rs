@@ -0,0 +1,400 @@
+use futures::{stream, StreamExt};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+use crate::addressManage::addressManage::Collector;
+use crate::addressManage::fetcher::{FailedSignature, FetchReport};
+use crate::addressManage::registry::WalletRegistry;
+use crate::error::Error;
+use crate::states::states::User;
+use crate::states::swap_event::{SwapEvent, TradeSide};
+use crate::utils::instruction_walker::DetectedSwap;
+use crate::utils::pump_parser::{bonding_curve_address, PumpEvent};
+use crate::utils::swap_decoder::DecodedTransaction;
+
+/*
+ *  从代币的早期买家中发现候选钱包
+ *  沿着 bonding curve(或者 mint)的签名历史一直往前翻到代币创建，按时间顺序解析其中的 pump.fun 买卖，
+ *  找出最早的 N 个买家与卖出之后已实现盈利最多的钱包，作为候选钱包交给 Collector 继续分析
+ */
+
+// 写入地址库时的发现来源与标签
+pub const DISCOVERY_SOURCE: &str = "token_discovery";
+pub const EARLY_BUYER_TAG: &str = "early_buyer";
+pub const TOP_EXIT_TAG: &str = "top_exit";
+
+/// 发现候选钱包的配置
+#[derive(Debug, Clone)]
+pub struct DiscoveryConfig {
+    pub first_buyers: usize,      // 取最早的多少个买家
+    pub top_exits: usize,         // 取已实现盈利最多的多少个卖出者
+    pub max_signatures: usize,    // 最多翻多少个签名，热门代币的历史可能非常长
+    pub walk_bonding_curve: bool, // true 时翻 bonding curve 的签名历史，否则翻 mint 的
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            first_buyers: 20,
+            top_exits: 20,
+            max_signatures: 5_000,
+            walk_bonding_curve: true,
+        }
+    }
+}
+
+/// 一个钱包在该代币上的买卖汇总
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenTrader {
+    pub address: Pubkey,
+    pub buy_rank: Option<usize>, // 第几个买入的钱包(从 0 开始)，没有买入为 None
+    pub first_buy_slot: Option<u64>,
+    pub bought: u128,      // 买入的代币数量(最小单位)
+    pub sold: u128,        // 卖出的代币数量(最小单位)
+    pub sol_spent: u64,    // 买入花费的 lamports
+    pub sol_received: u64, // 卖出收到的 lamports
+    pub events: Vec<SwapEvent>,
+    pub estimated: Vec<SwapEvent>, // 同一笔交易中有多个 swap，金额只能按指令限额估算的买卖，不计入盈亏
+}
+
+impl TokenTrader {
+    fn new(address: Pubkey) -> Self {
+        Self {
+            address,
+            buy_rank: None,
+            first_buy_slot: None,
+            bought: 0,
+            sold: 0,
+            sol_spent: 0,
+            sol_received: 0,
+            events: vec![],
+            estimated: vec![],
+        }
+    }
+
+    // 记录一次买卖，估算的买卖只保留事件，不计入数量与金额
+    fn record(&mut self, event: SwapEvent) {
+        if event.estimated {
+            self.estimated.push(event);
+            return;
+        }
+        match event.side {
+            TradeSide::Buy => {
+                self.bought += event.token_amount.amount;
+                self.sol_spent += event.quote_amount.amount as u64;
+            }
+            TradeSide::Sell => {
+                self.sold += event.token_amount.amount;
+                self.sol_received += event.quote_amount.amount as u64;
+            }
+        }
+        self.events.push(event);
+    }
+
+    /// 已实现盈亏(lamports)
+    /// - 只统计在翻到的历史中买入过的部分，卖出收入与买入成本都按比例分摊到这部分代币
+    /// - 不包括金额是估算出来的买卖(`estimated`)
+    pub fn realized_pnl(&self) -> i64 {
+        let matched = self.sold.min(self.bought);
+        if matched == 0 {
+            return 0;
+        }
+        let received = self.sol_received as u128 * matched / self.sold;
+        let cost = self.sol_spent as u128 * matched / self.bought;
+        received as i64 - cost as i64
+    }
+}
+
+/// 一个代币的早期买家分析结果
+#[derive(Debug, Default)]
+pub struct DiscoveryReport {
+    pub mint: Pubkey,
+    pub creator: Option<Pubkey>, // 在历史中找到 create 指令时为代币创建者
+    pub reached_creation: bool,  // 是否翻到了签名历史的开头
+    pub fetch: FetchReport,
+    pub traders: Vec<TokenTrader>, // 按第一次出现的顺序
+    pub early_buyers: Vec<Pubkey>, // 最早的买家(不包括创建者)
+    pub top_exits: Vec<Pubkey>,    // 已实现盈利最多的卖出者，按盈利从高到低
+}
+
+impl DiscoveryReport {
+    /// 所有候选钱包(早期买家在前，去重)
+    pub fn candidates(&self) -> Vec<Pubkey> {
+        let mut candidates = self.early_buyers.clone();
+        for address in &self.top_exits {
+            if !candidates.contains(address) {
+                candidates.push(*address);
+            }
+        }
+        candidates
+    }
+
+    /// 候选钱包对应的 User，已经记录了在该代币上金额确定的买卖
+    pub fn users(&self, time_day: u8) -> Vec<User> {
+        self.candidates()
+            .iter()
+            .map(|address| {
+                let mut user = User::new(*address, time_day);
+                if let Some(trader) = self.trader(address) {
+                    for event in &trader.events {
+                        user.record_swap_event(event);
+                    }
+                }
+                user
+            })
+            .collect()
+    }
+
+    pub fn trader(&self, address: &Pubkey) -> Option<&TokenTrader> {
+        self.traders
+            .iter()
+            .find(|trader| trader.address == *address)
+    }
+
+    /// 把候选钱包加入地址库，返回新增的数量
+    /// - `first_seen` 发现的时间戳
+    pub fn register(&self, registry: &WalletRegistry, first_seen: u64) -> Result<usize, Error> {
+        let mut added = 0;
+        for address in self.candidates() {
+            let mut tags = vec![];
+            if self.early_buyers.contains(&address) {
+                tags.push(EARLY_BUYER_TAG.to_string());
+            }
+            if self.top_exits.contains(&address) {
+                tags.push(TOP_EXIT_TAG.to_string());
+            }
+            if registry.add_candidate(&address, DISCOVERY_SOURCE, first_seen, &tags)? {
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+}
+
+impl Collector {
+    /// 从代币的早期买家与盈利最多的卖出者中发现候选钱包
+    /// - `mint` pump.fun 代币的 mint
+    /// - 签名历史翻到开头或者达到 `max_signatures` 为止，之后按从旧到新的顺序解析交易
+    pub async fn discover_from_token(
+        &self,
+        mint: &Pubkey,
+        config: &DiscoveryConfig,
+    ) -> Result<DiscoveryReport, Error> {
+        let history_address = match config.walk_bonding_curve {
+            true => bonding_curve_address(mint),
+            false => *mint,
+        };
+        let mut history = User::new(history_address, 0);
+        self.get_history_tx(&mut history, config.max_signatures, None)
+            .await?;
+
+        let mut report = DiscoveryReport {
+            mint: *mint,
+            reached_creation: history.history_txs.len() < config.max_signatures,
+            ..Default::default()
+        };
+        // 签名列表是从新到旧的
+        let txs: Vec<String> = history.history_txs.into_iter().rev().collect();
+        let finalized_slot = self.cache_finalized_slot(&txs).await;
+        let mut results = stream::iter(txs.into_iter().map(|tx| async move {
+            let decoded = self.fetch_transaction(&tx, finalized_slot).await;
+            (tx, decoded)
+        }))
+        .buffered(self.fetch_config.concurrency.max(1));
+
+        let mut traders: HashMap<Pubkey, TokenTrader> = HashMap::new();
+        let mut order: Vec<Pubkey> = vec![];
+        let mut buyer_count = 0;
+        while let Some((tx, decoded)) = results.next().await {
+            let decoded = match decoded {
+                Ok(decoded) => decoded,
+                Err(error) => {
+                    report.fetch.failed.push(FailedSignature {
+                        signature: tx,
+                        error,
+                    });
+                    continue;
+                }
+            };
+            report.fetch.fetched += 1;
+
+            let (creator, events) = pump_trades(&decoded, mint);
+            if creator.is_some() {
+                report.creator = creator;
+            }
+            for event in events {
+                let trader = traders.entry(event.wallet).or_insert_with(|| {
+                    order.push(event.wallet);
+                    TokenTrader::new(event.wallet)
+                });
+                // 金额估算的买入同样算作早期买入
+                if event.side == TradeSide::Buy && trader.buy_rank.is_none() {
+                    trader.buy_rank = Some(buyer_count);
+                    trader.first_buy_slot = event.slot;
+                    buyer_count += 1;
+                }
+                trader.record(event);
+            }
+        }
+        report.traders = order
+            .iter()
+            .filter_map(|address| traders.remove(address))
+            .collect();
+
+        let mut buyers: Vec<&TokenTrader> = report
+            .traders
+            .iter()
+            .filter(|trader| trader.buy_rank.is_some() && Some(trader.address) != report.creator)
+            .collect();
+        buyers.sort_by_key(|trader| trader.buy_rank);
+        report.early_buyers = buyers
+            .iter()
+            .take(config.first_buyers)
+            .map(|trader| trader.address)
+            .collect();
+
+        let mut exits: Vec<&TokenTrader> = report
+            .traders
+            .iter()
+            .filter(|trader| trader.realized_pnl() > 0)
+            .collect();
+        exits.sort_by_key(|trader| std::cmp::Reverse(trader.realized_pnl()));
+        report.top_exits = exits
+            .iter()
+            .take(config.top_exits)
+            .map(|trader| trader.address)
+            .collect();
+        Ok(report)
+    }
+}
+
+// 交易中与 `mint` 相关的 pump.fun 创建者与买卖事件
+// 一个钱包在同一笔交易中有多个 swap 时金额只能按指令限额估算，事件的 `estimated` 由 swap_events 标记
+fn pump_trades(decoded: &DecodedTransaction, mint: &Pubkey) -> (Option<Pubkey>, Vec<SwapEvent>) {
+    let mut creator = None;
+    let mut wallets: Vec<Pubkey> = vec![];
+    for routed in decoded.routed_swaps() {
+        let DetectedSwap::Pump(event) = &routed.swap else {
+            continue;
+        };
+        if event.mint() != mint {
+            continue;
+        }
+        match event {
+            PumpEvent::Create { user, .. } => creator = Some(*user),
+            _ => {
+                if !wallets.contains(&routed.wallet) {
+                    wallets.push(routed.wallet);
+                }
+            }
+        }
+    }
+
+    let events = wallets
+        .iter()
+        .flat_map(|wallet| decoded.swap_events(wallet))
+        .filter(|event| event.mint == *mint)
+        .collect();
+    (creator, events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addressManage::fetcher::FetchConfig;
+    use crate::addressManage::source::FixtureSource;
+    use crate::states::swap_event::test_support::swap;
+    use solana_sdk::native_token::LAMPORTS_PER_SOL;
+    use std::path::Path;
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_discover_from_token() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/rpc");
+        let source = Arc::new(FixtureSource::new(dir));
+        let collector = Collector::with_source(11, FetchConfig::default(), source, None).unwrap();
+        let mint = Pubkey::from_str("HjQpoSuGewTosqSAhwGKKhm6g2UhPuhgKdBcsnBHpump").unwrap();
+        let first = Pubkey::from_str("H356FzDuxvVShAGWRqtjR5D5efWdYM2eoazydG21Mgrk").unwrap();
+        let second = Pubkey::from_str("7vYfnSRn3LgBf1eDh3dvXzJkCNeGL5c6LRcA3D9bkHYk").unwrap();
+
+        let report = collector
+            .discover_from_token(&mint, &DiscoveryConfig::default())
+            .await
+            .unwrap();
+        assert!(report.reached_creation);
+        assert_eq!(report.fetch.fetched, 4);
+        assert_eq!(report.early_buyers, vec![first, second]);
+        // 第二个钱包全部卖出赚了 2 sol，第一个钱包卖出一半: 2 - 1.5 / 2 = 1.25 sol
+        assert_eq!(report.top_exits, vec![second, first]);
+        assert_eq!(
+            report.trader(&first).unwrap().realized_pnl(),
+            LAMPORTS_PER_SOL as i64 * 5 / 4
+        );
+
+        let users = report.users(7);
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[1].swap_events.len(), 2);
+
+        let registry = WalletRegistry::open_in_memory().unwrap();
+        assert_eq!(report.register(&registry, 100).unwrap(), 2);
+        let record = registry.get(&first).unwrap().unwrap();
+        assert_eq!(record.source, DISCOVERY_SOURCE);
+        assert_eq!(record.tags, vec![EARLY_BUYER_TAG, TOP_EXIT_TAG]);
+
+        // 只取最早的一个买家
+        let config = DiscoveryConfig {
+            first_buyers: 1,
+            top_exits: 0,
+            ..Default::default()
+        };
+        let report = collector.discover_from_token(&mint, &config).await.unwrap();
+        assert_eq!(report.candidates(), vec![first]);
+    }
+
+    #[tokio::test]
+    async fn test_estimated_trades_excluded_from_pnl() {
+        let mint = Pubkey::new_unique();
+        let mut trader = TokenTrader::new(Pubkey::new_unique());
+        trader.record(swap(
+            mint,
+            TradeSide::Buy,
+            1_000,
+            LAMPORTS_PER_SOL as u128,
+            1,
+        ));
+        // 捆绑在一笔交易里的卖出，金额是 min_sol_output
+        let mut bundled_sell = swap(mint, TradeSide::Sell, 500, 1, 2);
+        bundled_sell.estimated = true;
+        trader.record(bundled_sell);
+        trader.record(swap(
+            mint,
+            TradeSide::Sell,
+            500,
+            LAMPORTS_PER_SOL as u128,
+            3,
+        ));
+        assert_eq!(trader.sold, 500);
+        assert_eq!(trader.events.len(), 2);
+        assert_eq!(trader.estimated.len(), 1);
+        assert_eq!(trader.realized_pnl(), LAMPORTS_PER_SOL as i64 / 2);
+
+        // 同一笔交易里先买后卖，两个 swap 的金额都只能取指令限额，get_token_txs 同样不计入盈亏
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/rpc");
+        let source = Arc::new(FixtureSource::new(dir));
+        let collector = Collector::with_source(11, FetchConfig::default(), source, None).unwrap();
+        let wallet = Pubkey::from_str("BLhEmtdA9vbeNyrqJTDAxdzR2C56euNQFArJfuhEHkYG").unwrap();
+        let mut user = User::new(wallet, 0);
+        user.history_txs = vec![
+            "4TGyMJwQ3UU3s3Z8BdkWxv2n1qPqoW5ii6HaVcWvoU2JnyDKTHd6het4u1q52VPsx1XAdSFs9JYFC3vqBi3ACNwz"
+                .to_string(),
+        ];
+        let report = collector.get_token_txs(&mut user).await.unwrap();
+        assert_eq!(report.fetched, 1);
+        assert_eq!(user.swap_events.len(), 2);
+        assert!(user.swap_events.iter().all(|event| event.estimated));
+        assert!(user.closed_trades().is_empty());
+        assert_eq!(user.total_profit, 0.0);
+        assert_eq!(user.total_cost, 0.0);
+    }
+}
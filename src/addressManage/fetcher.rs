@@ -0,0 +1,250 @@
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::rpc_custom_error::{
+    JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE, JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+};
+use solana_client::rpc_request::RpcError;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, sleep_until, Instant};
+
+use crate::error::Error;
+
+/*
+ *  rpc 请求的抓取引擎
+ *  1.限制同时进行的请求数量(并发上限)
+ *  2.按节点限制每秒的请求数量，避免被节点限流
+ *  3.遇到 429 与临时性错误时按指数退避重试，其他错误直接返回
+ */
+
+/// 抓取引擎的配置
+#[derive(Debug, Clone)]
+pub struct FetchConfig {
+    pub concurrency: usize,         // 同时进行的请求数量上限
+    pub requests_per_second: u32,   // 每个节点每秒最多的请求数量，0 表示不限制
+    pub max_retries: u32,           // 单个请求最多重试的次数
+    pub max_delay: Duration,        // 退避等待的上限
+    pub signatures_per_page: usize, // getSignaturesForAddress 每页请求的签名数量，节点最多返回 1000 个
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            requests_per_second: 10,
+            max_retries: 5,
+            max_delay: Duration::from_secs(30),
+            signatures_per_page: 1000,
+        }
+    }
+}
+
+/// 获取失败的交易签名以及失败原因
+#[derive(Debug)]
+pub struct FailedSignature {
+    pub signature: String,
+    pub error: Error,
+}
+
+/// 一批交易的获取结果
+#[derive(Debug, Default)]
+pub struct FetchReport {
+    pub fetched: usize,               // 成功获取的交易数量
+    pub failed: Vec<FailedSignature>, // 获取失败的交易，不影响其他交易
+}
+
+/// 单个节点的限速器，相邻两次请求之间至少间隔 1 / requests_per_second 秒
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Option<Duration>,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: u32) -> Self {
+        Self {
+            interval: (requests_per_second > 0)
+                .then(|| Duration::from_secs(1) / requests_per_second),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// 等待直到可以发出下一次请求
+    pub async fn acquire(&self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + interval;
+            slot
+        };
+        sleep_until(slot).await;
+    }
+}
+
+/// 第 `attempt` 次重试前等待的时间: base * 2^attempt，不超过 `max`
+pub fn backoff_delay(attempt: u32, base: Duration, max: Duration) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempt)).min(max)
+}
+
+/// 是否为可以重试的 rpc 错误(429、5xx、超时/连接错误、节点暂时不可用)
+pub fn is_retryable(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(err) => {
+            err.is_timeout()
+                || err.is_connect()
+                || err
+                    .status()
+                    .is_some_and(|status| status.as_u16() == 429 || status.is_server_error())
+        }
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+                || *code == JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE
+        }
+        _ => false,
+    }
+}
+
+/// 限速 + 重试执行一次请求
+/// - `base_delay` 第一次重试前等待的时间，之后每次翻倍
+/// - `retryable` 判断错误是否可以重试，不可重试的错误直接返回
+pub async fn retry_with_backoff<T, E, F, Fut>(
+    config: &FetchConfig,
+    limiter: &RateLimiter,
+    base_delay: Duration,
+    retryable: impl Fn(&E) -> bool,
+    mut request: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 0;
+    loop {
+        limiter.acquire().await;
+        match request().await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt < config.max_retries && retryable(&err) => {
+                sleep(backoff_delay(attempt, base_delay, config.max_delay)).await;
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addressManage::mock_rpc::{read_request, write_response};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_backoff_delay() {
+        let base = Duration::from_millis(100);
+        let max = Duration::from_secs(1);
+        assert_eq!(backoff_delay(0, base, max), Duration::from_millis(100));
+        assert_eq!(backoff_delay(2, base, max), Duration::from_millis(400));
+        assert_eq!(backoff_delay(10, base, max), max);
+        assert_eq!(backoff_delay(u32::MAX, base, max), max);
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff() {
+        let config = FetchConfig {
+            max_retries: 3,
+            ..FetchConfig::default()
+        };
+        let limiter = RateLimiter::new(0);
+        let base = Duration::from_millis(1);
+
+        // 前两次临时性错误，第三次成功
+        let calls = AtomicU32::new(0);
+        let result: Result<u32, &str> = retry_with_backoff(
+            &config,
+            &limiter,
+            base,
+            |err| *err == "429",
+            || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err("429"),
+                    n => Ok(n),
+                }
+            },
+        )
+        .await;
+        assert_eq!(result, Ok(2));
+
+        // 不可重试的错误直接返回
+        let calls = AtomicU32::new(0);
+        let result: Result<u32, &str> = retry_with_backoff(
+            &config,
+            &limiter,
+            base,
+            |err| *err == "429",
+            || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err("invalid params")
+            },
+        )
+        .await;
+        assert_eq!(result, Err("invalid params"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 超过重试次数后返回最后一次的错误
+        let calls = AtomicU32::new(0);
+        let result: Result<u32, &str> = retry_with_backoff(
+            &config,
+            &limiter,
+            base,
+            |_| true,
+            || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err("429")
+            },
+        )
+        .await;
+        assert_eq!(result, Err("429"));
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter() {
+        let limiter = RateLimiter::new(20);
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.acquire().await;
+        }
+        // 第 5 次请求至少在 4 * 50ms 之后
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    // 真实 http 响应的状态码转换成的 ClientError(RpcClient 自己会先重试几次 429，这里直接构造)
+    async fn http_error(status: &str) -> ClientError {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let status = status.to_string();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_request(&mut stream).await.unwrap();
+            write_response(&mut stream, &status, "").await.unwrap();
+        });
+        let response = solana_client::client_error::reqwest::Client::new()
+            .post(url)
+            .send()
+            .await
+            .unwrap();
+        ClientError::from(response.error_for_status().unwrap_err())
+    }
+
+    #[tokio::test]
+    async fn test_is_retryable() {
+        assert!(is_retryable(&http_error("429 Too Many Requests").await));
+        assert!(is_retryable(&http_error("503 Service Unavailable").await));
+        assert!(!is_retryable(&http_error("400 Bad Request").await));
+    }
+}
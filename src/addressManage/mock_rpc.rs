@@ -0,0 +1,254 @@
+use serde_json::{json, Value};
+use solana_sdk::{bs58, hash::Hash, pubkey::Pubkey, signature::Signature};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::addressManage::source::{FixtureSource, TransactionSource};
+
+/*
+ *  本地的 json-rpc 替身节点
+ *  在进程内启动一个 http 服务，用 FixtureSource 目录中录制的数据响应
+ *  getSignaturesForAddress、getTransaction、getAccountInfo、getLatestBlockhash、getSlot，
+ *  可以注入错误、延迟与 429 限流，用来端到端地测试真实的 RpcClient 调用路径
+ */
+
+/// 注入到某个 rpc 方法的故障，每个故障只生效一次
+#[derive(Debug, Clone)]
+pub enum Fault {
+    Error { code: i64, message: String }, // 返回 json-rpc 错误
+    Delay(Duration),                      // 延迟之后正常响应
+    RateLimited,                          // 返回 http 429
+}
+
+type FaultQueue = Arc<Mutex<HashMap<String, VecDeque<Fault>>>>;
+type Requests = Arc<Mutex<Vec<(String, Value)>>>;
+
+pub struct MockRpcServer {
+    pub url: String,
+    faults: FaultQueue,
+    requests: Requests, // 收到的请求方法与参数，按顺序记录
+    handle: JoinHandle<()>,
+}
+
+impl MockRpcServer {
+    /// 在随机端口上启动服务，数据目录结构与 FixtureSource 相同
+    pub async fn start(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let source = Arc::new(FixtureSource::new(dir));
+        let faults: FaultQueue = Arc::default();
+        let requests: Requests = Arc::default();
+
+        let handle = tokio::spawn({
+            let faults = faults.clone();
+            let requests = requests.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let source = source.clone();
+                    let faults = faults.clone();
+                    let requests = requests.clone();
+                    tokio::spawn(async move {
+                        let _ = serve_connection(stream, &source, &faults, &requests).await;
+                    });
+                }
+            }
+        });
+
+        Ok(Self {
+            url,
+            faults,
+            requests,
+            handle,
+        })
+    }
+
+    /// 让 `method` 的下一次请求触发故障，多次调用时按顺序生效
+    pub fn inject(&self, method: &str, fault: Fault) {
+        self.faults
+            .lock()
+            .unwrap()
+            .entry(method.to_string())
+            .or_default()
+            .push_back(fault);
+    }
+
+    /// `method` 收到的请求次数(包括注入故障的请求)
+    pub fn request_count(&self, method: &str) -> usize {
+        self.request_params(method).len()
+    }
+
+    /// `method` 收到的每个请求的参数，按顺序排列
+    pub fn request_params(&self, method: &str) -> Vec<Value> {
+        let requests = self.requests.lock().unwrap();
+        requests
+            .iter()
+            .filter(|(name, _)| name == method)
+            .map(|(_, params)| params.clone())
+            .collect()
+    }
+}
+
+impl Drop for MockRpcServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+// 处理一个连接上的请求，每个响应之后关闭连接
+async fn serve_connection(
+    mut stream: TcpStream,
+    source: &FixtureSource,
+    faults: &FaultQueue,
+    requests: &Mutex<Vec<(String, Value)>>,
+) -> io::Result<()> {
+    let (_, body) = read_request(&mut stream).await?;
+    let request: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    let method = request["method"].as_str().unwrap_or_default().to_string();
+    let id = request["id"].clone();
+    requests
+        .lock()
+        .unwrap()
+        .push((method.clone(), request["params"].clone()));
+
+    let fault = faults
+        .lock()
+        .unwrap()
+        .get_mut(&method)
+        .and_then(|queue| queue.pop_front());
+    if let Some(Fault::Delay(delay)) = &fault {
+        tokio::time::sleep(*delay).await;
+    }
+    let result = match fault {
+        Some(Fault::RateLimited) => {
+            return write_response(&mut stream, "429 Too Many Requests", "").await;
+        }
+        Some(Fault::Error { code, message }) => Err((code, message)),
+        _ => handle_request(source, &method, &request["params"])
+            .await
+            .ok_or_else(|| (-32601, format!("Method not found: {}", method))),
+    };
+
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "error": { "code": code, "message": message },
+            "id": id,
+        }),
+    };
+    write_response(&mut stream, "200 OK", &response.to_string()).await
+}
+
+// 根据方法名从 fixture 中取数据，不支持的方法返回 None
+async fn handle_request(source: &FixtureSource, method: &str, params: &Value) -> Option<Value> {
+    let pubkey_param = || Pubkey::from_str(params[0].as_str()?).ok();
+    let signature_param = |key: &str| Signature::from_str(params[1][key].as_str()?).ok();
+    let context = json!({ "slot": FIXTURE_SLOT });
+
+    let result = match method {
+        "getSignaturesForAddress" => {
+            let limit = params[1]["limit"].as_u64().unwrap_or(1000) as usize;
+            let statuses = source
+                .get_signatures(
+                    &pubkey_param()?,
+                    signature_param("before"),
+                    signature_param("until"),
+                    limit,
+                )
+                .await
+                .ok()?;
+            json!(statuses)
+        }
+        // 没有录制的交易与节点一样返回 null
+        "getTransaction" => {
+            let signature = Signature::from_str(params[0].as_str()?).ok()?;
+            match source.get_transaction(&signature).await {
+                Ok(transaction) => json!(transaction),
+                Err(_) => Value::Null,
+            }
+        }
+        "getAccountInfo" => {
+            let value = match source.get_account_data(&pubkey_param()?).await {
+                Ok(data) => json!({
+                    "data": [bs58::encode(&data).into_string(), "base58"],
+                    "executable": false,
+                    "lamports": 1_000_000_000u64,
+                    "owner": solana_sdk::address_lookup_table::program::id().to_string(),
+                    "rentEpoch": u64::MAX,
+                    "space": data.len(),
+                }),
+                Err(_) => Value::Null,
+            };
+            json!({ "context": context, "value": value })
+        }
+        "getLatestBlockhash" => json!({
+            "context": context,
+            "value": {
+                "blockhash": Hash::default().to_string(),
+                "lastValidBlockHeight": FIXTURE_SLOT + 150,
+            },
+        }),
+        "getSlot" => json!(FIXTURE_SLOT),
+        _ => return None,
+    };
+    Some(result)
+}
+
+// 替身节点当前的 slot，比录制的交易都新
+const FIXTURE_SLOT: u64 = 400_000_000;
+
+// 读取 http 请求，返回请求头(请求行 + headers)与 body(按 Content-Length)
+pub(crate) async fn read_request(stream: &mut TcpStream) -> io::Result<(String, Vec<u8>)> {
+    let mut buffer = vec![];
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let content_length = head
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("content-length").then_some(value)
+        })
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while buffer.len() < header_end + content_length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    Ok((head, buffer[header_end..].to_vec()))
+}
+
+pub(crate) async fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    body: &str,
+) -> io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -1 +1,10 @@
+pub mod activity_feed;
 pub mod addressManage;
+pub mod discovery;
+pub mod fetcher;
+#[cfg(test)]
+pub mod mock_rpc;
+pub mod price_provider;
+pub mod registry;
+pub mod source;
+pub mod tx_store;
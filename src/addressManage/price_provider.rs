@@ -0,0 +1,350 @@
+use async_trait::async_trait;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::addressManage::source::{is_account_not_found, TransactionSource};
+use crate::constant::WSOL_MINT_PUBKEY;
+use crate::error::Error;
+use crate::states::states::User;
+use crate::utils::pump_curve::BondingCurve;
+use crate::utils::pump_parser::bonding_curve_address;
+
+/*
+ *  代币当前价格
+ *  用于把钱包还没卖出的持仓按市价估值(mark-to-market)，价格统一为每个代币值多少 sol。
+ *  OnChainPriceProvider 读取 pump.fun bonding curve 与 raydium 池子的储备计算价格，
+ *  FixturePriceProvider 使用固定的价格，用于离线分析与测试
+ */
+
+/// 获取代币当前的价格(sol/代币)
+#[async_trait]
+pub trait PriceProvider: Send + Sync {
+    /// 没有价格来源(比如没有配置池子的已迁移代币)时返回 None
+    async fn get_price(&self, mint: &Pubkey) -> Result<Option<f64>, Error>;
+}
+
+// raydium amm v4 池子账户中用到的字段
+const POOL_BASE_DECIMAL_OFFSET: usize = 32;
+const POOL_QUOTE_DECIMAL_OFFSET: usize = 40;
+const POOL_BASE_NEED_TAKE_PNL_OFFSET: usize = 192;
+const POOL_QUOTE_NEED_TAKE_PNL_OFFSET: usize = 200;
+const POOL_BASE_VAULT_OFFSET: usize = 336;
+const POOL_QUOTE_VAULT_OFFSET: usize = 368;
+const POOL_BASE_MINT_OFFSET: usize = 400;
+const POOL_QUOTE_MINT_OFFSET: usize = 432;
+
+// spl token 账户中余额的位置
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+
+/// 读取链上储备计算价格
+/// - 还在 bonding curve 上的代币按虚拟储备计算
+/// - 已经迁移(complete)的代币使用 `pools` 中配置的 raydium amm v4 池子
+pub struct OnChainPriceProvider {
+    pub source: Arc<dyn TransactionSource>,
+    pub pools: HashMap<Pubkey, Pubkey>, // 代币 mint => raydium 池子地址
+}
+
+impl OnChainPriceProvider {
+    pub fn new(source: Arc<dyn TransactionSource>) -> Self {
+        Self {
+            source,
+            pools: HashMap::new(),
+        }
+    }
+
+    /// 为代币指定 raydium 池子
+    pub fn with_pool(mut self, mint: Pubkey, pool: Pubkey) -> Self {
+        self.pools.insert(mint, pool);
+        self
+    }
+
+    // 读取账户数据，账户不存在时返回 None，其他错误(超时、限流等)返回 Error
+    async fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>, Error> {
+        match self.source.get_account_data(address).await {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if is_account_not_found(&err, address) => Ok(None),
+            Err(err) => Err(Error::PriceError(format!(
+                "get account {}: {:?}",
+                address, err
+            ))),
+        }
+    }
+
+    // 按池子中 sol 与代币的储备计算价格
+    async fn pool_price(&self, mint: &Pubkey, pool: &Pubkey) -> Result<Option<f64>, Error> {
+        let Some(data) = self.account_data(pool).await? else {
+            return Ok(None);
+        };
+        let invalid = || Error::PriceError(format!("invalid raydium pool account {}", pool));
+        let base_mint = read_pubkey(&data, POOL_BASE_MINT_OFFSET).ok_or_else(invalid)?;
+        let quote_mint = read_pubkey(&data, POOL_QUOTE_MINT_OFFSET).ok_or_else(invalid)?;
+        let base_reserve = self
+            .vault_reserve(
+                &data,
+                POOL_BASE_VAULT_OFFSET,
+                POOL_BASE_NEED_TAKE_PNL_OFFSET,
+            )
+            .await?
+            .ok_or_else(invalid)?;
+        let quote_reserve = self
+            .vault_reserve(
+                &data,
+                POOL_QUOTE_VAULT_OFFSET,
+                POOL_QUOTE_NEED_TAKE_PNL_OFFSET,
+            )
+            .await?
+            .ok_or_else(invalid)?;
+        let base_decimals = read_u64(&data, POOL_BASE_DECIMAL_OFFSET).ok_or_else(invalid)? as i32;
+        let quote_decimals = read_u64(&data, POOL_QUOTE_DECIMAL_OFFSET).ok_or_else(invalid)? as i32;
+
+        let (token_reserve, token_decimals, sol_reserve, sol_decimals) =
+            if base_mint == *mint && quote_mint == WSOL_MINT_PUBKEY {
+                (base_reserve, base_decimals, quote_reserve, quote_decimals)
+            } else if quote_mint == *mint && base_mint == WSOL_MINT_PUBKEY {
+                (quote_reserve, quote_decimals, base_reserve, base_decimals)
+            } else {
+                return Err(Error::PriceError(format!(
+                    "raydium pool {} is not a {}/sol pool",
+                    pool, mint
+                )));
+            };
+        Ok(reserve_price(
+            sol_reserve,
+            sol_decimals,
+            token_reserve,
+            token_decimals,
+        ))
+    }
+
+    // 池子金库的余额减去还没有提取的手续费，池子或者金库的数据不完整时为 None
+    async fn vault_reserve(
+        &self,
+        pool: &[u8],
+        vault_offset: usize,
+        pnl_offset: usize,
+    ) -> Result<Option<u64>, Error> {
+        let Some(vault) = read_pubkey(pool, vault_offset) else {
+            return Ok(None);
+        };
+        let Some(vault_data) = self.account_data(&vault).await? else {
+            return Ok(None);
+        };
+        let amount = read_u64(&vault_data, TOKEN_ACCOUNT_AMOUNT_OFFSET);
+        let pnl = read_u64(pool, pnl_offset);
+        Ok(amount
+            .zip(pnl)
+            .map(|(amount, pnl)| amount.saturating_sub(pnl)))
+    }
+}
+
+#[async_trait]
+impl PriceProvider for OnChainPriceProvider {
+    async fn get_price(&self, mint: &Pubkey) -> Result<Option<f64>, Error> {
+        let curve = bonding_curve_address(mint);
+        if let Some(data) = self.account_data(&curve).await? {
+            let curve = BondingCurve::decode(&data).map_err(|err| {
+                Error::PriceError(format!(
+                    "invalid bonding curve account {}: {:?}",
+                    curve, err
+                ))
+            })?;
+            if !curve.complete {
+                return Ok(curve.price());
+            }
+        }
+        // 不在 bonding curve 上或者已经迁移
+        match self.pools.get(mint) {
+            Some(pool) => self.pool_price(mint, pool).await,
+            None => Ok(None),
+        }
+    }
+}
+
+/// 固定的价格，用于离线分析与测试
+#[derive(Debug, Clone, Default)]
+pub struct FixturePriceProvider {
+    pub prices: HashMap<Pubkey, f64>,
+}
+
+impl FixturePriceProvider {
+    pub fn new(prices: HashMap<Pubkey, f64>) -> Self {
+        Self { prices }
+    }
+
+    /// 从 json 文件读取价格: {"<mint>": <sol 价格>, ...}
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = fs::read(path)
+            .map_err(|err| Error::PriceError(format!("{}: {:?}", path.display(), err)))?;
+        let prices: HashMap<String, f64> = serde_json::from_slice(&data)
+            .map_err(|err| Error::PriceError(format!("{}: {:?}", path.display(), err)))?;
+        let prices = prices
+            .into_iter()
+            .map(|(mint, price)| {
+                Pubkey::from_str(&mint)
+                    .map(|mint| (mint, price))
+                    .map_err(|_| Error::PriceError(format!("invalid mint {}", mint)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { prices })
+    }
+}
+
+#[async_trait]
+impl PriceProvider for FixturePriceProvider {
+    async fn get_price(&self, mint: &Pubkey) -> Result<Option<f64>, Error> {
+        Ok(self.prices.get(mint).copied())
+    }
+}
+
+/// 按当前价格给用户还没卖出的持仓估值，更新每个代币与用户的未实现盈亏
+/// - 拿不到价格的代币只计入持仓成本，记录在 `User::unpriced_mints` 中
+pub async fn mark_to_market(user: &mut User, provider: &dyn PriceProvider) -> Result<(), Error> {
+    let mut prices = HashMap::new();
+    for (mint, stats) in &user.token_stats {
+        if stats.lots.open_amount() == 0 {
+            continue;
+        }
+        if let Some(price) = provider.get_price(mint).await? {
+            prices.insert(*mint, price);
+        }
+    }
+    user.mark_to_market(&prices);
+    Ok(())
+}
+
+// 储备换算成价格，代币储备为 0 时没有价格
+fn reserve_price(
+    sol_reserve: u64,
+    sol_decimals: i32,
+    token_reserve: u64,
+    token_decimals: i32,
+) -> Option<f64> {
+    if token_reserve == 0 {
+        return None;
+    }
+    let sol = sol_reserve as f64 / 10f64.powi(sol_decimals);
+    let token = token_reserve as f64 / 10f64.powi(token_decimals);
+    Some(sol / token)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Option<Pubkey> {
+    let bytes: [u8; 32] = data.get(offset..offset + 32)?.try_into().ok()?;
+    Some(Pubkey::new_from_array(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addressManage::addressManage::{get_default_address, Collector};
+    use crate::addressManage::fetcher::FetchConfig;
+    use crate::addressManage::source::FixtureSource;
+    use crate::utils::pump_curve::BONDING_CURVE_DISCRIMINATOR;
+
+    fn bonding_curve_data(virtual_token: u64, virtual_sol: u64, complete: bool) -> Vec<u8> {
+        let mut data = BONDING_CURVE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&virtual_token.to_le_bytes());
+        data.extend_from_slice(&virtual_sol.to_le_bytes());
+        data.extend_from_slice(&[0; 24]);
+        data.push(complete as u8);
+        data
+    }
+
+    fn token_account_data(amount: u64) -> Vec<u8> {
+        let mut data = vec![0u8; 165];
+        data[TOKEN_ACCOUNT_AMOUNT_OFFSET..72].copy_from_slice(&amount.to_le_bytes());
+        data
+    }
+
+    #[tokio::test]
+    async fn test_on_chain_price_provider() {
+        let dir = std::env::temp_dir().join(format!("price_provider_test_{}", std::process::id()));
+        let accounts = dir.join("accounts");
+        fs::create_dir_all(&accounts).unwrap();
+        let write = |address: &Pubkey, data: Vec<u8>| {
+            fs::write(accounts.join(format!("{}.bin", address)), data).unwrap();
+        };
+
+        // 还在 bonding curve 上: 30 sol / 1,000,000,000 个代币
+        let on_curve = Pubkey::new_unique();
+        write(
+            &bonding_curve_address(&on_curve),
+            bonding_curve_data(1_000_000_000_000_000, 30_000_000_000, false),
+        );
+
+        // 已经迁移到 raydium: 池子中 100 个 sol(其中 1 个是手续费) / 99,000,000 个代币
+        let migrated = Pubkey::new_unique();
+        let pool = Pubkey::new_unique();
+        let (base_vault, quote_vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        write(
+            &bonding_curve_address(&migrated),
+            bonding_curve_data(0, 0, true),
+        );
+        let mut pool_data = vec![0u8; 752];
+        pool_data[POOL_BASE_DECIMAL_OFFSET..40].copy_from_slice(&6u64.to_le_bytes());
+        pool_data[POOL_QUOTE_DECIMAL_OFFSET..48].copy_from_slice(&9u64.to_le_bytes());
+        pool_data[POOL_QUOTE_NEED_TAKE_PNL_OFFSET..208]
+            .copy_from_slice(&1_000_000_000u64.to_le_bytes());
+        pool_data[POOL_BASE_VAULT_OFFSET..368].copy_from_slice(base_vault.as_ref());
+        pool_data[POOL_QUOTE_VAULT_OFFSET..400].copy_from_slice(quote_vault.as_ref());
+        pool_data[POOL_BASE_MINT_OFFSET..432].copy_from_slice(migrated.as_ref());
+        pool_data[POOL_QUOTE_MINT_OFFSET..464].copy_from_slice(WSOL_MINT_PUBKEY.as_ref());
+        write(&pool, pool_data);
+        write(&base_vault, token_account_data(99_000_000_000_000));
+        write(&quote_vault, token_account_data(100_000_000_000));
+
+        let provider =
+            OnChainPriceProvider::new(Arc::new(FixtureSource::new(&dir))).with_pool(migrated, pool);
+        let price = provider.get_price(&on_curve).await.unwrap().unwrap();
+        assert!((price - 0.00000003).abs() < 1e-15);
+        let price = provider.get_price(&migrated).await.unwrap().unwrap();
+        assert!((price - 0.000001).abs() < 1e-15);
+        // 没有 bonding curve 也没有池子
+        assert_eq!(
+            provider.get_price(&Pubkey::new_unique()).await.unwrap(),
+            None
+        );
+
+        // 读取账户出错(不是账户不存在)时不能当作没有价格
+        let unreadable = Pubkey::new_unique();
+        fs::create_dir_all(accounts.join(format!("{}.bin", bonding_curve_address(&unreadable))))
+            .unwrap();
+        assert!(matches!(
+            provider.get_price(&unreadable).await,
+            Err(Error::PriceError(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mark_to_market() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        let source = Arc::new(FixtureSource::new(root.join("rpc")));
+        let collector = Collector::with_source(11, FetchConfig::default(), source, None).unwrap();
+        let mut user = User::new(get_default_address(), 0);
+        collector
+            .get_history_tx(&mut user, 100, None)
+            .await
+            .unwrap();
+        collector.get_token_txs(&mut user).await.unwrap();
+
+        // 还剩 1,000,000 个代币，成本 0.75 sol，现在值 0.5 sol
+        let provider = FixturePriceProvider::load(root.join("prices.json")).unwrap();
+        mark_to_market(&mut user, &provider).await.unwrap();
+        assert_eq!(user.open_cost, 0.75);
+        assert!((user.unrealized_pnl + 0.25).abs() < 1e-9);
+        // (1.25 - 0.25) / (0.75 + 0.75)
+        user.count_balance_change();
+        assert!((user.balance_change - 1.0 / 1.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,478 @@
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use solana_sdk::pubkey::Pubkey;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::Error;
+use crate::utils::analyze_utils::RankedWallet;
+
+/*
+ *  聪明钱包候选地址库(sqlite)
+ *  记录每个候选钱包的标签、发现来源、首次发现时间、最近一次分析的时间与分析到的 slot、最新评分与跟单状态，
+ *  Collector 按批次读取需要分析的钱包，分析/评分的结果再写回来
+ */
+
+/// 钱包的跟单状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowStatus {
+    Candidate,   // 候选，还没有决定是否跟单
+    Following,   // 正在跟单
+    Paused,      // 暂停跟单
+    Blacklisted, // 拉黑，不再分析
+}
+
+impl FollowStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FollowStatus::Candidate => "candidate",
+            FollowStatus::Following => "following",
+            FollowStatus::Paused => "paused",
+            FollowStatus::Blacklisted => "blacklisted",
+        }
+    }
+}
+
+impl FromStr for FollowStatus {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "candidate" => Ok(FollowStatus::Candidate),
+            "following" => Ok(FollowStatus::Following),
+            "paused" => Ok(FollowStatus::Paused),
+            "blacklisted" => Ok(FollowStatus::Blacklisted),
+            other => Err(Error::RegistryError(format!(
+                "unknown follow status {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// 地址库中的一个钱包
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletRecord {
+    pub address: Pubkey,
+    pub tags: Vec<String>,               // 标签，比如 "pump"、"sniper"
+    pub source: String,                  // 发现来源，比如 "early_buyer"、"manual"
+    pub first_seen: u64,                 // 首次发现的时间戳
+    pub last_analyzed_slot: Option<u64>, // 最近一次分析到的交易所在 slot，没有交易时为 None
+    pub last_analyzed_at: Option<u64>,   // 最近一次分析的时间戳，没分析过为 None
+    pub score: Option<f64>,              // 最新评分
+    pub follow_status: FollowStatus,
+}
+
+impl WalletRecord {
+    pub fn new(address: Pubkey, source: &str, first_seen: u64) -> Self {
+        Self {
+            address,
+            tags: vec![],
+            source: source.to_string(),
+            first_seen,
+            last_analyzed_slot: None,
+            last_analyzed_at: None,
+            score: None,
+            follow_status: FollowStatus::Candidate,
+        }
+    }
+}
+
+/// 查询条件，为 None 的条件不过滤，结果按评分从高到低排序
+#[derive(Debug, Clone, Default)]
+pub struct WalletQuery {
+    pub follow_status: Option<FollowStatus>,
+    pub tag: Option<String>,
+    pub source: Option<String>,
+    pub min_score: Option<f64>,
+    pub limit: Option<usize>,
+}
+
+pub struct WalletRegistry {
+    conn: Connection,
+}
+
+const SELECT_COLUMNS: &str =
+    "address, tags, source, first_seen, last_analyzed_slot, last_analyzed_at, score, follow_status";
+
+impl WalletRegistry {
+    /// 打开(不存在时创建)地址库
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::init(Connection::open(path).map_err(registry_error)?)
+    }
+
+    /// 内存中的地址库，用于测试
+    pub fn open_in_memory() -> Result<Self, Error> {
+        Self::init(Connection::open_in_memory().map_err(registry_error)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, Error> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS wallets (
+                address            TEXT PRIMARY KEY,
+                tags               TEXT NOT NULL DEFAULT '[]',
+                source             TEXT NOT NULL,
+                first_seen         INTEGER NOT NULL,
+                last_analyzed_slot INTEGER,
+                last_analyzed_at   INTEGER,
+                score              REAL,
+                follow_status      TEXT NOT NULL DEFAULT 'candidate'
+            );
+            CREATE INDEX IF NOT EXISTS wallets_score ON wallets (score);",
+        )
+        .map_err(registry_error)?;
+        // 旧版本的地址库没有 last_analyzed_at 列
+        if conn
+            .prepare("SELECT last_analyzed_at FROM wallets LIMIT 0")
+            .is_err()
+        {
+            conn.execute(
+                "ALTER TABLE wallets ADD COLUMN last_analyzed_at INTEGER",
+                [],
+            )
+            .map_err(registry_error)?;
+        }
+        Ok(Self { conn })
+    }
+
+    /// 新增或者覆盖一个钱包，已存在时保留更早的 first_seen
+    pub fn upsert(&self, record: &WalletRecord) -> Result<(), Error> {
+        self.conn
+            .execute(
+                "INSERT INTO wallets (address, tags, source, first_seen, last_analyzed_slot, last_analyzed_at, score, follow_status)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT(address) DO UPDATE SET
+                    tags = excluded.tags,
+                    source = excluded.source,
+                    first_seen = MIN(first_seen, excluded.first_seen),
+                    last_analyzed_slot = excluded.last_analyzed_slot,
+                    last_analyzed_at = excluded.last_analyzed_at,
+                    score = excluded.score,
+                    follow_status = excluded.follow_status",
+                params![
+                    record.address.to_string(),
+                    serde_json::to_string(&record.tags).unwrap_or_else(|_| "[]".to_string()),
+                    record.source,
+                    record.first_seen as i64,
+                    record.last_analyzed_slot.map(|slot| slot as i64),
+                    record.last_analyzed_at.map(|time| time as i64),
+                    record.score,
+                    record.follow_status.as_str(),
+                ],
+            )
+            .map_err(registry_error)?;
+        Ok(())
+    }
+
+    /// 新发现的候选钱包，已经存在时不做修改，返回是否新增
+    pub fn add_candidate(
+        &self,
+        address: &Pubkey,
+        source: &str,
+        first_seen: u64,
+        tags: &[String],
+    ) -> Result<bool, Error> {
+        let inserted = self
+            .conn
+            .execute(
+                "INSERT OR IGNORE INTO wallets (address, tags, source, first_seen) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    address.to_string(),
+                    serde_json::to_string(tags).unwrap_or_else(|_| "[]".to_string()),
+                    source,
+                    first_seen as i64,
+                ],
+            )
+            .map_err(registry_error)?;
+        Ok(inserted > 0)
+    }
+
+    pub fn get(&self, address: &Pubkey) -> Result<Option<WalletRecord>, Error> {
+        self.conn
+            .query_row(
+                &format!("SELECT {} FROM wallets WHERE address = ?1", SELECT_COLUMNS),
+                params![address.to_string()],
+                read_record,
+            )
+            .optional()
+            .map_err(registry_error)?
+            .transpose()
+    }
+
+    /// 删除钱包，返回是否存在
+    pub fn remove(&self, address: &Pubkey) -> Result<bool, Error> {
+        let removed = self
+            .conn
+            .execute(
+                "DELETE FROM wallets WHERE address = ?1",
+                params![address.to_string()],
+            )
+            .map_err(registry_error)?;
+        Ok(removed > 0)
+    }
+
+    pub fn set_tags(&self, address: &Pubkey, tags: &[String]) -> Result<bool, Error> {
+        self.update(
+            "UPDATE wallets SET tags = ?2 WHERE address = ?1",
+            address,
+            serde_json::to_string(tags).unwrap_or_else(|_| "[]".to_string()),
+        )
+    }
+
+    pub fn set_follow_status(&self, address: &Pubkey, status: FollowStatus) -> Result<bool, Error> {
+        self.update(
+            "UPDATE wallets SET follow_status = ?2 WHERE address = ?1",
+            address,
+            status.as_str(),
+        )
+    }
+
+    /// 写回一次分析的结果
+    /// - `slot` 本次分析到的最新交易所在 slot，为 None 时保留原来的值
+    /// - `analyzed_at` 本次分析的时间戳，下一批次按它排序
+    pub fn record_analysis(
+        &self,
+        address: &Pubkey,
+        slot: Option<u64>,
+        score: f64,
+        analyzed_at: u64,
+    ) -> Result<bool, Error> {
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE wallets SET score = ?2, last_analyzed_slot = COALESCE(?3, last_analyzed_slot),
+                    last_analyzed_at = ?4
+                 WHERE address = ?1",
+                params![
+                    address.to_string(),
+                    score,
+                    slot.map(|slot| slot as i64),
+                    analyzed_at as i64,
+                ],
+            )
+            .map_err(registry_error)?;
+        Ok(updated > 0)
+    }
+
+    /// 写回 WalletAnalyzer 的排名结果，不在地址库中的钱包作为候选钱包加入
+    /// - `source` 新加入钱包的发现来源
+    /// - 每个钱包写回它自己买卖中最新的 slot
+    pub fn record_ranking(&self, ranked: &[RankedWallet], source: &str) -> Result<(), Error> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let transaction = self.conn.unchecked_transaction().map_err(registry_error)?;
+        for wallet in ranked {
+            self.add_candidate(&wallet.address, source, now, &[])?;
+            self.record_analysis(&wallet.address, wallet.latest_slot, wallet.score, now)?;
+        }
+        transaction.commit().map_err(registry_error)
+    }
+
+    fn update(
+        &self,
+        sql: &str,
+        address: &Pubkey,
+        value: impl rusqlite::ToSql,
+    ) -> Result<bool, Error> {
+        let updated = self
+            .conn
+            .execute(sql, params![address.to_string(), value])
+            .map_err(registry_error)?;
+        Ok(updated > 0)
+    }
+
+    /// 按条件查询钱包，结果按评分从高到低排序(没有评分的排在最后)
+    pub fn query(&self, query: &WalletQuery) -> Result<Vec<WalletRecord>, Error> {
+        let mut conditions = vec!["1 = 1".to_string()];
+        let mut values: Vec<rusqlite::types::Value> = vec![];
+        if let Some(status) = query.follow_status {
+            values.push(status.as_str().to_string().into());
+            conditions.push(format!("follow_status = ?{}", values.len()));
+        }
+        if let Some(tag) = &query.tag {
+            values.push(tag.clone().into());
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM json_each(wallets.tags) WHERE value = ?{})",
+                values.len()
+            ));
+        }
+        if let Some(source) = &query.source {
+            values.push(source.clone().into());
+            conditions.push(format!("source = ?{}", values.len()));
+        }
+        if let Some(min_score) = query.min_score {
+            values.push(min_score.into());
+            conditions.push(format!("score >= ?{}", values.len()));
+        }
+        let limit = query.limit.map(|limit| limit as i64).unwrap_or(-1);
+
+        let sql = format!(
+            "SELECT {} FROM wallets WHERE {} ORDER BY score IS NULL, score DESC, address LIMIT {}",
+            SELECT_COLUMNS,
+            conditions.join(" AND "),
+            limit
+        );
+        self.collect(&sql, values)
+    }
+
+    /// 下一批需要分析的钱包: 跳过拉黑的钱包，从没分析过的排在最前，其次是最久没有分析的
+    pub fn next_batch(&self, limit: usize) -> Result<Vec<WalletRecord>, Error> {
+        let sql = format!(
+            "SELECT {} FROM wallets WHERE follow_status != 'blacklisted'
+             ORDER BY last_analyzed_at IS NOT NULL, last_analyzed_at, first_seen LIMIT ?1",
+            SELECT_COLUMNS
+        );
+        self.collect(&sql, vec![(limit as i64).into()])
+    }
+
+    fn collect(
+        &self,
+        sql: &str,
+        values: Vec<rusqlite::types::Value>,
+    ) -> Result<Vec<WalletRecord>, Error> {
+        let mut statement = self.conn.prepare(sql).map_err(registry_error)?;
+        let rows = statement
+            .query_map(params_from_iter(values), read_record)
+            .map_err(registry_error)?;
+        rows.map(|row| row.map_err(registry_error)?).collect()
+    }
+}
+
+fn registry_error(err: rusqlite::Error) -> Error {
+    Error::RegistryError(format!("{:?}", err))
+}
+
+// 读取一行，地址与状态不合法时返回 Error(放在内层，数据库错误放在外层)
+fn read_record(row: &Row) -> rusqlite::Result<Result<WalletRecord, Error>> {
+    let address: String = row.get(0)?;
+    let tags: String = row.get(1)?;
+    let status: String = row.get(7)?;
+    let source = row.get(2)?;
+    let first_seen: i64 = row.get(3)?;
+    let last_analyzed_slot: Option<i64> = row.get(4)?;
+    let last_analyzed_at: Option<i64> = row.get(5)?;
+    let score = row.get(6)?;
+
+    Ok((|| {
+        Ok(WalletRecord {
+            address: Pubkey::from_str(&address)
+                .map_err(|_| Error::RegistryError(format!("invalid address {}", address)))?,
+            tags: serde_json::from_str(&tags)
+                .map_err(|_| Error::RegistryError(format!("invalid tags {}", tags)))?,
+            source,
+            first_seen: first_seen as u64,
+            last_analyzed_slot: last_analyzed_slot.map(|slot| slot as u64),
+            last_analyzed_at: last_analyzed_at.map(|time| time as u64),
+            score,
+            follow_status: FollowStatus::from_str(&status)?,
+        })
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::analyze_utils::TradeCosts;
+
+    #[test]
+    fn test_wallet_registry() {
+        let registry = WalletRegistry::open_in_memory().unwrap();
+        let wallets: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let tags = vec!["pump".to_string()];
+
+        assert!(registry
+            .add_candidate(&wallets[0], "early_buyer", 100, &tags)
+            .unwrap());
+        assert!(!registry
+            .add_candidate(&wallets[0], "manual", 50, &[])
+            .unwrap());
+        assert!(registry
+            .add_candidate(&wallets[1], "manual", 200, &[])
+            .unwrap());
+        assert!(registry
+            .add_candidate(&wallets[2], "manual", 300, &tags)
+            .unwrap());
+
+        let record = registry.get(&wallets[0]).unwrap().unwrap();
+        assert_eq!(record.source, "early_buyer");
+        assert_eq!(record.tags, tags);
+        assert_eq!(record.follow_status, FollowStatus::Candidate);
+
+        // 按分析时间排序，没有交易(slot 为 None)的钱包同样记录分析时间，拉黑的钱包不再分析
+        registry
+            .record_analysis(&wallets[0], Some(1_000), 3.5, 2_000)
+            .unwrap();
+        registry
+            .record_analysis(&wallets[1], None, 1.0, 1_000)
+            .unwrap();
+        registry
+            .set_follow_status(&wallets[2], FollowStatus::Blacklisted)
+            .unwrap();
+        let batch: Vec<Pubkey> = registry
+            .next_batch(10)
+            .unwrap()
+            .iter()
+            .map(|record| record.address)
+            .collect();
+        assert_eq!(batch, vec![wallets[1], wallets[0]]);
+
+        let ranked = registry.query(&WalletQuery::default()).unwrap();
+        assert_eq!(ranked[0].address, wallets[0]);
+        assert_eq!(ranked[0].last_analyzed_slot, Some(1_000));
+        assert_eq!(ranked[0].last_analyzed_at, Some(2_000));
+        assert_eq!(ranked[1].last_analyzed_slot, None);
+        assert_eq!(ranked[1].last_analyzed_at, Some(1_000));
+        assert_eq!(ranked[2].score, None);
+
+        let pump = registry
+            .query(&WalletQuery {
+                tag: Some("pump".to_string()),
+                min_score: Some(2.0),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(pump.len(), 1);
+        assert_eq!(pump[0].address, wallets[0]);
+
+        // upsert 保留更早的 first_seen
+        let mut record = registry.get(&wallets[1]).unwrap().unwrap();
+        record.first_seen = 500;
+        record.follow_status = FollowStatus::Following;
+        registry.upsert(&record).unwrap();
+        let record = registry.get(&wallets[1]).unwrap().unwrap();
+        assert_eq!(record.first_seen, 200);
+        assert_eq!(record.follow_status, FollowStatus::Following);
+
+        assert!(registry.remove(&wallets[1]).unwrap());
+        assert!(registry.get(&wallets[1]).unwrap().is_none());
+
+        // 排名结果写回，新的钱包作为候选加入
+        let ranked = RankedWallet {
+            address: Pubkey::new_unique(),
+            gross_pnl: 0,
+            costs: TradeCosts::default(),
+            net_pnl: 0,
+            trade_count: 2,
+            token_count: 1,
+            win_rate: 1.0,
+            score: 0.5,
+            latest_slot: Some(2_000),
+        };
+        let other = RankedWallet {
+            address: Pubkey::new_unique(),
+            latest_slot: Some(1_500),
+            ..ranked.clone()
+        };
+        registry
+            .record_ranking(&[ranked.clone(), other.clone()], "analyzer")
+            .unwrap();
+        let record = registry.get(&ranked.address).unwrap().unwrap();
+        assert_eq!(record.source, "analyzer");
+        assert_eq!(record.score, Some(0.5));
+        assert_eq!(record.last_analyzed_slot, Some(2_000));
+        let record = registry.get(&other.address).unwrap().unwrap();
+        assert_eq!(record.last_analyzed_slot, Some(1_500));
+    }
+}
@@ -0,0 +1,261 @@
+use async_trait::async_trait;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_client::rpc_request::RpcError;
+use solana_client::rpc_response::RpcConfirmedTransactionStatusWithSignature;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status_client_types::{
+    EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/*
+ *  交易数据源
+ *  Collector 只通过 TransactionSource 获取签名列表、交易与账户数据，
+ *  RpcSource 请求链上节点，FixtureSource 读取目录中录制好的 json，用于离线、可重复的分析与测试
+ */
+
+#[async_trait]
+pub trait TransactionSource: Send + Sync {
+    /// 按 getSignaturesForAddress 的语义获取地址的交易签名(从新到旧)
+    /// - `before` 只返回比该签名更旧的交易
+    /// - `until` 只返回比该签名更新的交易
+    async fn get_signatures(
+        &self,
+        address: &Pubkey,
+        before: Option<Signature>,
+        until: Option<Signature>,
+        limit: usize,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError>;
+
+    /// 获取交易的原始数据(base58 编码的交易 + meta)
+    async fn get_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, ClientError>;
+
+    /// 获取账户数据(用于读取地址查找表)
+    /// - 账户不存在时返回 `account_not_found` 的错误
+    async fn get_account_data(&self, address: &Pubkey) -> Result<Vec<u8>, ClientError>;
+
+    /// 最新的 finalized slot
+    async fn get_finalized_slot(&self) -> Result<u64, ClientError>;
+}
+
+/// 账户不存在时的错误，与 RpcClient 的一致
+pub fn account_not_found(address: &Pubkey) -> ClientError {
+    RpcError::ForUser(format!("AccountNotFound: pubkey={}", address)).into()
+}
+
+/// 是否为账户不存在的错误
+/// - RpcClient 把请求失败(超时、429 等)也包装成 "AccountNotFound: pubkey=..: <原因>"，
+///   只有完全相同的消息才是账户不存在
+pub fn is_account_not_found(err: &ClientError, address: &Pubkey) -> bool {
+    match err.kind() {
+        ClientErrorKind::RpcError(RpcError::ForUser(message)) => {
+            *message == format!("AccountNotFound: pubkey={}", address)
+        }
+        _ => false,
+    }
+}
+
+/// 通过 rpc 节点获取数据
+pub struct RpcSource {
+    pub client: RpcClient,
+}
+
+impl RpcSource {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: RpcClient::new(url),
+        }
+    }
+}
+
+#[async_trait]
+impl TransactionSource for RpcSource {
+    async fn get_signatures(
+        &self,
+        address: &Pubkey,
+        before: Option<Signature>,
+        until: Option<Signature>,
+        limit: usize,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError> {
+        let config = GetConfirmedSignaturesForAddress2Config {
+            before,
+            until,
+            limit: Some(limit),
+            commitment: Some(CommitmentConfig::confirmed()),
+        };
+        self.client
+            .get_signatures_for_address_with_config(address, config)
+            .await
+    }
+
+    async fn get_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, ClientError> {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base58),
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        };
+        self.client
+            .get_transaction_with_config(signature, config)
+            .await
+    }
+
+    async fn get_account_data(&self, address: &Pubkey) -> Result<Vec<u8>, ClientError> {
+        self.client.get_account_data(address).await
+    }
+
+    async fn get_finalized_slot(&self) -> Result<u64, ClientError> {
+        self.client
+            .get_slot_with_commitment(CommitmentConfig::finalized())
+            .await
+    }
+}
+
+/// 从目录中读取录制好的数据，目录结构:
+/// - `signatures/<address>.json` 地址的签名列表(getSignaturesForAddress 的结果，从新到旧)
+/// - `transactions/<signature>.json` getTransaction 的结果(base58 编码)
+/// - `accounts/<address>.bin` 账户的原始数据
+///
+/// 录制的数据都视为已经 finalized
+#[derive(Debug, Clone)]
+pub struct FixtureSource {
+    pub dir: PathBuf,
+}
+
+impl FixtureSource {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+}
+
+// 缺少 fixture 文件不是临时性错误，不使用 Io 错误以免被重试
+fn fixture_error(path: &Path, err: std::io::Error) -> ClientError {
+    ClientErrorKind::Custom(format!("fixture {} not found: {}", path.display(), err)).into()
+}
+
+#[async_trait]
+impl TransactionSource for FixtureSource {
+    async fn get_signatures(
+        &self,
+        address: &Pubkey,
+        before: Option<Signature>,
+        until: Option<Signature>,
+        limit: usize,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError> {
+        let path = self
+            .dir
+            .join("signatures")
+            .join(format!("{}.json", address));
+        // 没有录制的地址视为没有交易
+        let statuses: Vec<RpcConfirmedTransactionStatusWithSignature> = match path.exists() {
+            true => {
+                serde_json::from_slice(&fs::read(&path).map_err(|err| fixture_error(&path, err))?)?
+            }
+            false => vec![],
+        };
+        Ok(page_signatures(statuses, before, until, limit))
+    }
+
+    async fn get_transaction(
+        &self,
+        signature: &Signature,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, ClientError> {
+        let path = self
+            .dir
+            .join("transactions")
+            .join(format!("{}.json", signature));
+        Ok(serde_json::from_slice(
+            &fs::read(&path).map_err(|err| fixture_error(&path, err))?,
+        )?)
+    }
+
+    async fn get_account_data(&self, address: &Pubkey) -> Result<Vec<u8>, ClientError> {
+        let path = self.dir.join("accounts").join(format!("{}.bin", address));
+        fs::read(&path).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => account_not_found(address),
+            _ => fixture_error(&path, err),
+        })
+    }
+
+    async fn get_finalized_slot(&self) -> Result<u64, ClientError> {
+        Ok(u64::MAX)
+    }
+}
+
+/// 在从新到旧排列的签名列表上实现 before/until/limit 分页
+pub fn page_signatures(
+    statuses: Vec<RpcConfirmedTransactionStatusWithSignature>,
+    before: Option<Signature>,
+    until: Option<Signature>,
+    limit: usize,
+) -> Vec<RpcConfirmedTransactionStatusWithSignature> {
+    let before = before.map(|signature| signature.to_string());
+    let until = until.map(|signature| signature.to_string());
+    let start = match &before {
+        Some(before) => match statuses.iter().position(|s| &s.signature == before) {
+            Some(position) => position + 1,
+            None => return vec![],
+        },
+        None => 0,
+    };
+    statuses
+        .into_iter()
+        .skip(start)
+        .take_while(|status| Some(&status.signature) != until.as_ref())
+        .take(limit)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_signatures() {
+        let signatures: Vec<Signature> = (1..=5u8).map(|n| Signature::from([n; 64])).collect();
+        let statuses: Vec<RpcConfirmedTransactionStatusWithSignature> = signatures
+            .iter()
+            .map(|signature| RpcConfirmedTransactionStatusWithSignature {
+                signature: signature.to_string(),
+                slot: 0,
+                err: None,
+                memo: None,
+                block_time: None,
+                confirmation_status: None,
+            })
+            .collect();
+        let page = |before, until, limit| -> Vec<String> {
+            page_signatures(statuses.clone(), before, until, limit)
+                .into_iter()
+                .map(|status| status.signature)
+                .collect()
+        };
+
+        assert_eq!(
+            page(None, None, 2),
+            [signatures[0], signatures[1]].map(|s| s.to_string())
+        );
+        assert_eq!(
+            page(Some(signatures[1]), None, 10),
+            [signatures[2], signatures[3], signatures[4]].map(|s| s.to_string())
+        );
+        assert_eq!(
+            page(None, Some(signatures[2]), 10),
+            [signatures[0], signatures[1]].map(|s| s.to_string())
+        );
+        assert!(page(Some(signatures[4]), None, 10).is_empty());
+    }
+}
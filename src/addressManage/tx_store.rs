@@ -0,0 +1,115 @@
+use solana_transaction_status_client_types::EncodedConfirmedTransactionWithStatusMeta;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/*
+ *  本地交易缓存
+ *  已经确认的交易不会再改变，按签名把 getTransaction 的原始结果(编码后的交易 + meta)保存在磁盘上，
+ *  Collector 获取交易前先查缓存，重复分析同一批钱包时基本不需要再请求 rpc
+ */
+
+/// 什么样的交易可以写入缓存
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    Disabled,  // 不写入缓存(已有的缓存仍然可以读取)
+    Confirmed, // confirmed 的交易就写入缓存，极少数情况下可能被回滚
+    Finalized, // 只缓存 slot 不超过最新 finalized slot 的交易
+}
+
+impl CachePolicy {
+    /// 交易是否可以写入缓存
+    /// - `slot` 交易所在的 slot
+    /// - `finalized_slot` 最新的 finalized slot，获取失败时为 None
+    pub fn should_cache(&self, slot: u64, finalized_slot: Option<u64>) -> bool {
+        match self {
+            CachePolicy::Disabled => false,
+            CachePolicy::Confirmed => true,
+            CachePolicy::Finalized => finalized_slot.is_some_and(|finalized| slot <= finalized),
+        }
+    }
+}
+
+/// 以签名为 key 的磁盘交易缓存，每笔交易保存为 `<dir>/<signature>.json`
+#[derive(Debug, Clone)]
+pub struct TransactionStore {
+    pub dir: PathBuf,
+    pub policy: CachePolicy,
+}
+
+impl TransactionStore {
+    /// 打开(不存在时创建)缓存目录
+    pub fn open(dir: impl AsRef<Path>, policy: CachePolicy) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            policy,
+        })
+    }
+
+    fn path(&self, signature: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", signature))
+    }
+
+    /// 读取缓存的交易，不存在或者文件损坏时返回 None
+    pub fn get(&self, signature: &str) -> Option<EncodedConfirmedTransactionWithStatusMeta> {
+        let data = fs::read(self.path(signature)).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    pub fn contains(&self, signature: &str) -> bool {
+        self.path(signature).exists()
+    }
+
+    /// 按缓存策略写入交易，返回是否写入
+    /// 先写临时文件再重命名，避免中断时留下不完整的缓存
+    pub fn put(
+        &self,
+        signature: &str,
+        transaction: &EncodedConfirmedTransactionWithStatusMeta,
+        finalized_slot: Option<u64>,
+    ) -> anyhow::Result<bool> {
+        if !self.policy.should_cache(transaction.slot, finalized_slot) {
+            return Ok(false);
+        }
+        let path = self.path(signature);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(transaction)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_transaction_store() {
+        let dir = std::env::temp_dir().join(format!("tx_store_test_{}", std::process::id()));
+        let store = TransactionStore::open(&dir, CachePolicy::Finalized).unwrap();
+        let transaction: EncodedConfirmedTransactionWithStatusMeta =
+            serde_json::from_value(json!({
+                "slot": 100,
+                "blockTime": 1736812854,
+                "transaction": ["3Bxs4NN8M2Yn4TLb", "base58"],
+                "meta": null,
+                "version": 0
+            }))
+            .unwrap();
+
+        // 还没有 finalized 的交易不写入
+        assert!(!store.put("sig", &transaction, Some(99)).unwrap());
+        assert!(!store.put("sig", &transaction, None).unwrap());
+        assert!(store.get("sig").is_none());
+
+        assert!(store.put("sig", &transaction, Some(100)).unwrap());
+        assert!(store.contains("sig"));
+        assert_eq!(store.get("sig"), Some(transaction));
+
+        assert!(CachePolicy::Confirmed.should_cache(100, None));
+        assert!(!CachePolicy::Disabled.should_cache(100, Some(200)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,9 +3,23 @@ pub enum Error {
     GetHistoryTxError(String),
     HttpRequestError,
     GetTokenTxError(String),
+    ResolveAccountKeysError(String),
+    ActivityFeedError(String),
+    InvalidActivity(TxParseError),
+    RegistryError(String), // 地址库读写失败
+    PriceError(String),    // 获取代币价格失败
+    ConfigError(String),   // 读取配置失败
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TxParseError {
     InvalidField(String),
+    InvalidInstruction(String),
+    Schema(String),                                  // 记录不符合活动接口的格式
+    MissingField(String),                            // 缺少必须的字段
+    InvalidDecimal { field: String, value: String }, // 十进制字符串格式不对或者超出精度
+    UnsupportedChain(String),
+    UnknownEventType(String),
+    InvalidQuoteToken(String), // 不是以 sol/wsol 计价
+    InvalidAccount(String),    // 账户数据格式不对
 }
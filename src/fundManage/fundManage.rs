@@ -5,7 +5,7 @@ use std::collections::HashMap;
 use std::fmt::Display;
 use crate::states::states::User;
 
-/**
+/*
  *  资金管理模块
  *  当前模块负责：
  *  1.管理当前配置账户的资金
@@ -15,7 +15,7 @@ use crate::states::states::User;
  */
 
 
-/// todo!: 现在没做的点：1.实时监控的结构还没有设计 2.事件触发的结构也没设计 3.对每个仓位应该做风险控制
+// todo!: 现在没做的点：1.实时监控的结构还没有设计 2.事件触发的结构也没设计 3.对每个仓位应该做风险控制
 
 /// 跟单事件
 #[derive(Debug, Clone)]
@@ -180,6 +180,7 @@ pub struct HistoryPosition {
 
 #[cfg(test)]
 mod tests {
+    #[allow(unused_imports)]
     use super::*;
 
 }
@@ -1,3 +1,4 @@
 pub mod fundManage;
 pub mod strategy;
+pub mod tiering;
 pub mod trade;
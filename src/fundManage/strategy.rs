@@ -1,5 +1,4 @@
 use std::fmt::Display;
-use std::ptr::write;
 
 /// 跟单策略
 #[derive(Debug, Clone)]
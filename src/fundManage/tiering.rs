@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::fs;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::fundManage::fundManage::SmartWallet;
+use crate::fundManage::strategy::CopyTradeStrategy;
+use crate::states::states::User;
+
+/*
+ *  钱包分级
+ *  打分之后的 User 先经过硬性条件筛选(已平仓的交易太少、盈利太低或者太久没有交易的钱包直接淘汰)，
+ *  剩下的按评分对照等级表分级，不同等级分配不同的跟单资金与每个仓位的资金比例，
+ *  比如 A 级钱包每个仓位 5%，B 级钱包每个仓位 3%，达不到最低等级的钱包不跟单
+ */
+
+/// 硬性筛选条件
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TierFilter {
+    pub min_trades: usize,      // 最少已平仓的交易笔数(有买入记录的卖出)
+    pub min_profit: f64,        // 最少已实现盈利(sol)
+    pub max_inactive_secs: u64, // 最后一次买卖距今最多多少秒
+}
+
+impl Default for TierFilter {
+    fn default() -> Self {
+        Self {
+            min_trades: 5,
+            min_profit: 0.0,
+            max_inactive_secs: 7 * 24 * 3600,
+        }
+    }
+}
+
+/// 等级表中的一个等级
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tier {
+    pub name: String,
+    pub min_score: f64,          // 评分不低于该值时属于该等级
+    pub allocate_funds: f64,     // 分配给该等级每个钱包的最大可跟单金额(sol)
+    pub per_position_funds: f64, // 每个仓位跟单的比例(具体金额：allocate_funds * per_position_funds)
+}
+
+/// 分级配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TieringConfig {
+    #[serde(default)]
+    pub filter: TierFilter,
+    pub tiers: Vec<Tier>,
+}
+
+impl Default for TieringConfig {
+    fn default() -> Self {
+        let tier = |name: &str, min_score, allocate_funds, per_position_funds| Tier {
+            name: name.to_string(),
+            min_score,
+            allocate_funds,
+            per_position_funds,
+        };
+        Self {
+            filter: TierFilter::default(),
+            tiers: vec![tier("A", 70.0, 1.0, 0.05), tier("B", 50.0, 0.5, 0.03)],
+        }
+    }
+}
+
+impl TieringConfig {
+    /// 从 json 文件读取: {"filter": {...}, "tiers": [{"name": "A", "min_score": 70, ...}, ...]}
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = fs::read(path)
+            .map_err(|err| Error::ConfigError(format!("{}: {:?}", path.display(), err)))?;
+        serde_json::from_slice(&data)
+            .map_err(|err| Error::ConfigError(format!("{}: {:?}", path.display(), err)))
+    }
+}
+
+/// 钱包被淘汰的原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum Disqualification {
+    TooFewTrades { trades: usize, min: usize },
+    LowProfit { profit: f64, min: f64 },
+    Inactive { idle_secs: u64, max: u64 }, // 没有任何买卖时 idle_secs 为 u64::MAX
+    BelowLowestTier { score: f64 },
+}
+
+/// 分级之后的聪明钱包
+#[derive(Debug, Clone)]
+pub struct TieredWallet {
+    pub tier: String,
+    pub wallet: SmartWallet,
+}
+
+/// 分级结果
+#[derive(Debug, Clone, Default)]
+pub struct TieringReport {
+    pub wallets: Vec<TieredWallet>, // 按评分从高到低
+    pub rejected: Vec<(Pubkey, Disqualification)>,
+}
+
+/// 分级引擎
+pub struct TieringEngine {
+    pub config: TieringConfig,
+}
+
+impl TieringEngine {
+    /// - 等级表按 `min_score` 从高到低排序
+    pub fn new(mut config: TieringConfig) -> Self {
+        config
+            .tiers
+            .sort_by(|a, b| b.min_score.total_cmp(&a.min_score));
+        Self { config }
+    }
+
+    /// 对打分之后的钱包筛选并分级
+    /// - `now` 当前的 unix 时间戳，用于判断多久没有交易
+    pub fn assign(&self, users: Vec<User>, now: u64) -> TieringReport {
+        let mut report = TieringReport::default();
+        for user in users {
+            if let Err(reason) = self.check(&user, now) {
+                report.rejected.push((user.address, reason));
+                continue;
+            }
+            let Some(tier) = self.tier_of(user.score) else {
+                report.rejected.push((
+                    user.address,
+                    Disqualification::BelowLowestTier { score: user.score },
+                ));
+                continue;
+            };
+
+            let mut strategy = CopyTradeStrategy::get_default_strategy();
+            strategy.allocate_funds = tier.allocate_funds;
+            strategy.per_position_funds = tier.per_position_funds;
+            report.wallets.push(TieredWallet {
+                tier: tier.name.clone(),
+                wallet: SmartWallet::new(user, tier.allocate_funds, strategy),
+            });
+        }
+        report
+            .wallets
+            .sort_by(|a, b| b.wallet.score.total_cmp(&a.wallet.score));
+        report
+    }
+
+    /// 评分对应的等级，低于所有等级时为 None
+    pub fn tier_of(&self, score: f64) -> Option<&Tier> {
+        self.config
+            .tiers
+            .iter()
+            .find(|tier| score >= tier.min_score)
+    }
+
+    // 硬性条件，按已平仓的交易笔数、盈利、活跃度的顺序检查
+    fn check(&self, user: &User, now: u64) -> Result<(), Disqualification> {
+        let filter = &self.config.filter;
+        let trades = user.closed_trades().len();
+        if trades < filter.min_trades {
+            return Err(Disqualification::TooFewTrades {
+                trades,
+                min: filter.min_trades,
+            });
+        }
+        if user.total_profit < filter.min_profit {
+            return Err(Disqualification::LowProfit {
+                profit: user.total_profit,
+                min: filter.min_profit,
+            });
+        }
+        let last_trade = user.swap_events.iter().map(|event| event.block_time).max();
+        let idle_secs = last_trade.map_or(u64::MAX, |last| now.saturating_sub(last));
+        if idle_secs > filter.max_inactive_secs {
+            return Err(Disqualification::Inactive {
+                idle_secs,
+                max: filter.max_inactive_secs,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::states::swap_event::test_support::swap;
+    use crate::states::swap_event::TradeSide;
+
+    // 每一轮 1 sol 买入，30 秒之后 `proceeds` lamports 全部卖出
+    fn user(score: f64, rounds: usize, proceeds: u128, last_trade: u64) -> User {
+        let mut user = User::new(Pubkey::new_unique(), 7);
+        let mut events = vec![];
+        for i in 0..rounds {
+            let mint = Pubkey::new_unique();
+            let sold_at = last_trade - (rounds - i) as u64 * 60;
+            let bought_at = sold_at - 30;
+            events.push(swap(mint, TradeSide::Buy, 1_000, 1_000_000_000, bought_at));
+            events.push(swap(mint, TradeSide::Sell, 1_000, proceeds, sold_at));
+        }
+        user.record_swap_events(events);
+        user.score = score;
+        user
+    }
+
+    #[test]
+    fn test_assign_tiers() {
+        let now = 10_000_000;
+        let engine = TieringEngine::new(TieringConfig::default());
+        let (a, b, low, few, losing, idle) = (
+            user(60.0, 5, 2_000_000_000, now),
+            user(80.0, 5, 2_000_000_000, now),
+            user(30.0, 5, 2_000_000_000, now),
+            user(90.0, 4, 2_000_000_000, now),
+            user(90.0, 5, 500_000_000, now),
+            user(90.0, 5, 2_000_000_000, now - 8 * 24 * 3600),
+        );
+        let expected_rejected = vec![
+            (
+                low.address,
+                Disqualification::BelowLowestTier { score: 30.0 },
+            ),
+            (
+                few.address,
+                Disqualification::TooFewTrades { trades: 4, min: 5 },
+            ),
+            (
+                losing.address,
+                Disqualification::LowProfit {
+                    profit: -2.5,
+                    min: 0.0,
+                },
+            ),
+            (
+                idle.address,
+                Disqualification::Inactive {
+                    idle_secs: 8 * 24 * 3600 + 60,
+                    max: 7 * 24 * 3600,
+                },
+            ),
+        ];
+        let (a_address, b_address) = (a.address, b.address);
+
+        let report = engine.assign(vec![a, b, low, few, losing, idle], now);
+        assert_eq!(report.rejected, expected_rejected);
+        let tiers: Vec<(&str, Pubkey)> = report
+            .wallets
+            .iter()
+            .map(|tiered| (tiered.tier.as_str(), tiered.wallet.address))
+            .collect();
+        assert_eq!(tiers, vec![("A", b_address), ("B", a_address)]);
+
+        let wallet = &report.wallets[1].wallet;
+        assert_eq!(wallet.allocate_funds, 0.5);
+        assert_eq!(wallet.strategy.allocate_funds, 0.5);
+        assert_eq!(wallet.strategy.per_position_funds, 0.03);
+    }
+}
@@ -2,6 +2,6 @@ use crate::error::Error;
 use crate::fundManage::fundManage::*;
 
 /// 跟单
-pub fn copy_order(smart_wallet: SmartWallet) -> Result<(), Error> {
+pub fn copy_order(_smart_wallet: SmartWallet) -> Result<(), Error> {
     Ok(())
 }
@@ -1,3 +1,5 @@
+#![allow(non_snake_case, clippy::module_inception)]
+
 pub mod addressManage;
 
 pub mod fundManage;
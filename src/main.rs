@@ -9,8 +9,6 @@
  *   3.我们依据排名，将整个地址进行划分等级，不同的等级，分配不同的金额(这里可能会存在一个进一步筛选钱包的过程，比如营收太低或者频率太低直接pass)
  *   4.对具体的仓位分别资金，比如说等级为A的钱包，一个仓位分为5%，等级为B的钱包，一个仓位分为3%
  */
-use std::result;
-
 #[tokio::main]
 async fn main() {
     println!("Hello, world!");
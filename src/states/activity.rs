@@ -0,0 +1,287 @@
+use serde::Deserialize;
+use serde_json::Value;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::str::FromStr;
+
+use crate::constant::WSOL_MINT_PUBKEY;
+use crate::error::TxParseError;
+use crate::states::swap_event::{SwapEvent, TokenAmount, TradeSide, Venue};
+
+/*
+ *  钱包活动接口的记录格式
+ *  数量、价格都是十进制字符串，按精度精确解析；字段缺失或者格式不对时返回具体的 TxParseError，不再默认成 0
+ */
+
+// 接口中原生 sol 的地址(与 wsol 一样按 wsol 计价处理)
+pub const NATIVE_SOL_ADDRESS: &str = "So11111111111111111111111111111111111111111";
+// 不是买卖的活动类型，直接跳过
+const NON_SWAP_EVENT_TYPES: [&str; 3] = ["transfer", "add", "remove"];
+
+/// 活动中的代币信息
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ActivityToken {
+    pub address: String,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub logo: Option<String>,
+}
+
+/// 活动中的计价代币信息
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ActivityQuoteToken {
+    pub token_address: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    pub decimals: u8,
+    #[serde(default)]
+    pub logo: Option<String>,
+}
+
+/// 一条钱包活动记录
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WalletActivity {
+    pub chain: String,
+    pub tx_hash: String,
+    pub timestamp: u64,
+    pub event_type: String,
+    pub token: ActivityToken,
+    pub token_amount: String, // 十进制字符串
+    pub quote_amount: String, // 十进制字符串
+    #[serde(default)]
+    pub cost_usd: Option<String>, // 当前行为花的钱/收到的钱
+    #[serde(default)]
+    pub buy_cost_usd: Option<String>, // 买入时为 null，卖出时为买入花的钱
+    #[serde(default)]
+    pub price_usd: Option<String>,
+    #[serde(default)]
+    pub is_open_or_close: Option<i64>,
+    pub quote_token: ActivityQuoteToken,
+    #[serde(default)]
+    pub from_address: String,
+    #[serde(default)]
+    pub to_address: String,
+}
+
+impl WalletActivity {
+    /// 按 schema 解析 json，字段缺失或者类型不对时返回 TxParseError::Schema
+    pub fn from_value(value: &Value) -> Result<Self, TxParseError> {
+        Self::deserialize(value).map_err(|err| TxParseError::Schema(err.to_string()))
+    }
+
+    /// 校验并转换成 SwapEvent
+    /// - 只支持 sol 链、以 sol/wsol 计价的买卖
+    /// - transfer/add/remove 等非买卖的活动返回 None
+    pub fn to_swap_event(&self, wallet: &Pubkey) -> Result<Option<SwapEvent>, TxParseError> {
+        if self.chain != "sol" {
+            return Err(TxParseError::UnsupportedChain(self.chain.clone()));
+        }
+        let side = match self.event_type.as_str() {
+            "buy" => TradeSide::Buy,
+            "sell" => TradeSide::Sell,
+            other if NON_SWAP_EVENT_TYPES.contains(&other) => return Ok(None),
+            other => return Err(TxParseError::UnknownEventType(other.to_string())),
+        };
+
+        let quote_address = self.quote_token.token_address.as_str();
+        if quote_address != NATIVE_SOL_ADDRESS && quote_address != WSOL_MINT_PUBKEY.to_string() {
+            return Err(TxParseError::InvalidQuoteToken(quote_address.to_string()));
+        }
+        if self.quote_token.decimals != 9 {
+            return Err(TxParseError::InvalidQuoteToken(format!(
+                "{} with {} decimals",
+                quote_address, self.quote_token.decimals
+            )));
+        }
+
+        let signature = Signature::from_str(&self.tx_hash)
+            .map_err(|_| TxParseError::InvalidField(format!("tx_hash: {}", self.tx_hash)))?;
+        let mint = Pubkey::from_str(&self.token.address).map_err(|_| {
+            TxParseError::InvalidField(format!("token.address: {}", self.token.address))
+        })?;
+        let token_amount = parse_amount("token_amount", &self.token_amount, None)?;
+        let quote_amount = parse_amount(
+            "quote_amount",
+            &self.quote_amount,
+            Some(self.quote_token.decimals),
+        )?;
+
+        let value_usd = self
+            .cost_usd
+            .as_deref()
+            .map(|value| parse_usd("cost_usd", value))
+            .transpose()?;
+        // 盈亏按自己的持仓成本计算，数据源的买入成本只作参考，卖出时没有也不影响
+        let cost_basis_usd = match side {
+            TradeSide::Buy => None,
+            TradeSide::Sell => self
+                .buy_cost_usd
+                .as_deref()
+                .map(|value| parse_usd("buy_cost_usd", value))
+                .transpose()?,
+        };
+
+        Ok(Some(SwapEvent {
+            wallet: *wallet,
+            mint,
+            side,
+            price: SwapEvent::compute_price(&token_amount, &quote_amount),
+            token_amount,
+            quote_amount,
+            quote_mint: WSOL_MINT_PUBKEY,
+            slot: None,
+            block_time: self.timestamp,
+            venue: Venue::Unknown,
+            signature: signature.to_string(),
+            route: vec![],
+            value_usd,
+            cost_basis_usd,
+            estimated: false,
+        }))
+    }
+}
+
+fn parse_amount(
+    field: &str,
+    value: &str,
+    decimals: Option<u8>,
+) -> Result<TokenAmount, TxParseError> {
+    TokenAmount::parse_decimal(value, decimals).ok_or_else(|| TxParseError::InvalidDecimal {
+        field: field.to_string(),
+        value: value.to_string(),
+    })
+}
+
+fn parse_usd(field: &str, value: &str) -> Result<f64, TxParseError> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| TxParseError::InvalidDecimal {
+            field: field.to_string(),
+            value: value.to_string(),
+        })
+}
+
+/// 没有通过校验的活动记录
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectedActivity {
+    pub index: usize,            // 在数据源返回的列表中的下标
+    pub tx_hash: Option<String>, // 能读到 tx_hash 时记录下来方便排查
+    pub error: TxParseError,
+}
+
+/// 一个用户的活动记录校验结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActivityValidationReport {
+    pub total: usize,         // 收到的记录数量
+    pub accepted: usize,      // 转换成 SwapEvent 的记录数量
+    pub skipped: usize,       // 非买卖的记录数量
+    pub out_of_window: usize, // 早于时间窗口的买卖记录数量，不参与统计
+    pub rejected: Vec<RejectedActivity>,
+}
+
+impl ActivityValidationReport {
+    pub fn is_clean(&self) -> bool {
+        self.rejected.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn activity() -> Value {
+        json!({
+            "chain": "sol",
+            "tx_hash": "3Mma1rNFhjWk9R3V9sb1U31zFpaGX41QnuPgScXNssRBV6HZ2RXCYovr5uL3rXgZcg9mCciqMWAE7JHJDmkKotMg",
+            "timestamp": 1736442395,
+            "event_type": "sell",
+            "token": { "address": "6xmiC8Gsp6i8owu3JMDpt38vsCGznCmW5Fzjuomqpump", "symbol": "CHEETAH" },
+            "token_amount": "21060060.86858700000000000000",
+            "quote_amount": "4.32067168100000000000",
+            "cost_usd": "829.74178961924",
+            "buy_cost_usd": "375.59062821386",
+            "price_usd": "0.000039398831513036",
+            "is_open_or_close": 1,
+            "quote_token": {
+                "token_address": "So11111111111111111111111111111111111111112",
+                "symbol": "WSOL",
+                "decimals": 9
+            },
+            "from_address": "",
+            "to_address": ""
+        })
+    }
+
+    fn validate(value: Value) -> Result<Option<SwapEvent>, TxParseError> {
+        WalletActivity::from_value(&value)?.to_swap_event(&Pubkey::new_unique())
+    }
+
+    #[test]
+    fn test_activity_to_swap_event() {
+        let event = validate(activity()).unwrap().unwrap();
+        assert_eq!(event.side, TradeSide::Sell);
+        assert_eq!(event.token_amount, TokenAmount::new(21060060868587, 6));
+        assert_eq!(event.quote_amount, TokenAmount::new(4_320_671_681, 9));
+        assert_eq!(event.block_time, 1736442395);
+        assert_eq!(event.cost_basis_usd, Some(375.59062821386));
+
+        // 卖出时没有买入成本同样有效
+        let mut sell = activity();
+        sell["buy_cost_usd"] = Value::Null;
+        assert_eq!(validate(sell).unwrap().unwrap().cost_basis_usd, None);
+
+        let mut transfer = activity();
+        transfer["event_type"] = json!("transfer");
+        assert_eq!(validate(transfer), Ok(None));
+    }
+
+    #[test]
+    fn test_invalid_activities() {
+        let invalid = |field: &str, value: Value| {
+            let mut activity = activity();
+            activity[field] = value;
+            validate(activity).unwrap_err()
+        };
+
+        assert!(matches!(
+            invalid("timestamp", json!("yesterday")),
+            TxParseError::Schema(_)
+        ));
+        assert_eq!(
+            invalid("chain", json!("eth")),
+            TxParseError::UnsupportedChain("eth".to_string())
+        );
+        assert_eq!(
+            invalid("event_type", json!("mint")),
+            TxParseError::UnknownEventType("mint".to_string())
+        );
+        assert_eq!(
+            invalid("token_amount", json!("1.2e6")),
+            TxParseError::InvalidDecimal {
+                field: "token_amount".to_string(),
+                value: "1.2e6".to_string()
+            }
+        );
+        assert_eq!(
+            invalid("buy_cost_usd", json!("n/a")),
+            TxParseError::InvalidDecimal {
+                field: "buy_cost_usd".to_string(),
+                value: "n/a".to_string()
+            }
+        );
+        assert!(matches!(
+            invalid(
+                "quote_token",
+                json!({ "token_address": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "decimals": 6 })
+            ),
+            TxParseError::InvalidQuoteToken(_)
+        ));
+    }
+}
@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::states::swap_event::TokenAmount;
+
+/*
+ *  按批次(lot)记录持仓成本
+ *  每次买入形成一个 lot(代币数量 + 花费的计价代币)，卖出时按 FIFO 或者加权平均从 lot 中扣除成本，
+ *  卖出的已实现盈亏只依赖我们自己记录的买入，不再使用数据源给出的买入成本。
+ *  持有时间与成本计算方式无关，始终把卖出按 FIFO 匹配到每一次买入的时间
+ */
+
+/// 成本计算方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostBasisMethod {
+    #[default]
+    Fifo, // 先买入的先卖出
+    WeightedAverage, // 所有持仓按平均成本计算
+}
+
+/// 一次买入形成的持仓
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lot {
+    pub amount: u128,   // 剩余的代币数量(最小单位)
+    pub cost: u128,     // 剩余数量对应的成本(计价代币的最小单位)
+    pub timestamp: u64, // 买入时间，加权平均时为最近一次买入的时间(不用于计算持有时间)
+}
+
+/// 一次买入还没卖出的数量，用于计算持有时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenBuy {
+    pub amount: u128,   // 剩余的代币数量(精度与 lot 相同)
+    pub timestamp: u64, // 买入时间
+}
+
+/// 一次卖出的结算结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RealizedSell {
+    pub matched: u128,     // 有买入记录的卖出数量
+    pub unmatched: u128,   // 超出持仓的卖出数量(买入不在统计范围内)，不计算盈亏
+    pub cost_basis: u128,  // 匹配部分的买入成本
+    pub proceeds: u128,    // 匹配部分的卖出收入
+    pub pnl: i128,         // proceeds - cost_basis
+    pub holding_secs: u64, // 匹配部分按数量加权的持有时间(秒)，按 FIFO 匹配买入时间
+}
+
+/// 一个代币的持仓批次
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct LotBook {
+    pub method: CostBasisMethod,
+    pub decimals: u8, // lot 中代币数量的精度，遇到更高精度的数量时统一放大
+    pub lots: VecDeque<Lot>,
+    #[serde(default)]
+    pub open_buys: VecDeque<OpenBuy>, // 每次买入剩余的数量与时间，按先后顺序，与成本计算方式无关
+}
+
+impl LotBook {
+    pub fn new(method: CostBasisMethod) -> Self {
+        Self {
+            method,
+            decimals: 0,
+            lots: VecDeque::new(),
+            open_buys: VecDeque::new(),
+        }
+    }
+
+    /// 记录一次买入
+    /// - `cost` 花费的计价代币(最小单位)
+    pub fn buy(&mut self, amount: TokenAmount, cost: u128, timestamp: u64) {
+        let amount = self.normalize(amount);
+        if amount == 0 {
+            return;
+        }
+        self.open_buys.push_back(OpenBuy { amount, timestamp });
+        match (self.method, self.lots.front_mut()) {
+            (CostBasisMethod::WeightedAverage, Some(lot)) => {
+                lot.amount += amount;
+                lot.cost += cost;
+                lot.timestamp = timestamp;
+            }
+            _ => self.lots.push_back(Lot {
+                amount,
+                cost,
+                timestamp,
+            }),
+        }
+    }
+
+    /// 记录一次卖出，按成本计算方式从持仓中扣除成本
+    /// - `proceeds` 收到的计价代币(最小单位)，超出持仓的部分按比例不计入
+    /// - `timestamp` 卖出时间，用于计算持有时间
+    pub fn sell(&mut self, amount: TokenAmount, proceeds: u128, timestamp: u64) -> RealizedSell {
+        let amount = self.normalize(amount);
+        let weighted_secs = self.take_open_buys(amount, timestamp);
+        let mut remaining = amount;
+        let mut cost_basis = 0;
+        while remaining > 0 {
+            let Some(lot) = self.lots.front_mut() else {
+                break;
+            };
+            let take = remaining.min(lot.amount);
+            let cost = lot.cost * take / lot.amount;
+            lot.amount -= take;
+            lot.cost -= cost;
+            cost_basis += cost;
+            remaining -= take;
+            if lot.amount == 0 {
+                self.lots.pop_front();
+            }
+        }
+
+        let matched = amount - remaining;
+        let proceeds = match amount {
+            0 => 0,
+            _ => proceeds * matched / amount,
+        };
+        let holding_secs = match matched {
+            0 => 0,
+            _ => (weighted_secs / matched) as u64,
+        };
+        RealizedSell {
+            matched,
+            unmatched: remaining,
+            cost_basis,
+            proceeds,
+            pnl: proceeds as i128 - cost_basis as i128,
+            holding_secs,
+        }
+    }
+
+    // 按 FIFO 从买入记录中扣除卖出数量，返回按数量加权的持有时间总和
+    fn take_open_buys(&mut self, amount: u128, timestamp: u64) -> u128 {
+        let mut remaining = amount;
+        let mut weighted_secs = 0u128;
+        while remaining > 0 {
+            let Some(buy) = self.open_buys.front_mut() else {
+                break;
+            };
+            let take = remaining.min(buy.amount);
+            weighted_secs += take * timestamp.saturating_sub(buy.timestamp) as u128;
+            buy.amount -= take;
+            remaining -= take;
+            if buy.amount == 0 {
+                self.open_buys.pop_front();
+            }
+        }
+        weighted_secs
+    }
+
+    /// 当前持仓数量(精度为 `decimals`)
+    pub fn open_amount(&self) -> u128 {
+        self.lots.iter().map(|lot| lot.amount).sum()
+    }
+
+    /// 当前持仓的成本
+    pub fn open_cost(&self) -> u128 {
+        self.lots.iter().map(|lot| lot.cost).sum()
+    }
+
+    // 把数量换算到 lot 的精度，精度更高时先放大已有的 lot
+    fn normalize(&mut self, amount: TokenAmount) -> u128 {
+        if amount.decimals > self.decimals {
+            let scale = 10u128.pow((amount.decimals - self.decimals) as u32);
+            for lot in self.lots.iter_mut() {
+                lot.amount *= scale;
+            }
+            for buy in self.open_buys.iter_mut() {
+                buy.amount *= scale;
+            }
+            self.decimals = amount.decimals;
+        }
+        amount.amount * 10u128.pow((self.decimals - amount.decimals) as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(amount: u128) -> TokenAmount {
+        TokenAmount::new(amount, 6)
+    }
+
+    #[test]
+    fn test_fifo_and_weighted_average() {
+        // 先以 1 买入 100，再以 3 买入 100，卖出 150 收到 300
+        let trade = |method| {
+            let mut book = LotBook::new(method);
+            book.buy(tokens(100), 100, 1);
+            book.buy(tokens(100), 300, 2);
+            let sell = book.sell(tokens(150), 300, 10);
+            (sell, book.open_amount(), book.open_cost())
+        };
+
+        let (sell, open_amount, open_cost) = trade(CostBasisMethod::Fifo);
+        assert_eq!(sell.cost_basis, 100 + 150);
+        assert_eq!(sell.pnl, 50);
+        // 100 个持有 9 秒，50 个持有 8 秒
+        assert_eq!(sell.holding_secs, (100 * 9 + 50 * 8) / 150);
+        assert_eq!((open_amount, open_cost), (50, 150));
+
+        let (sell, open_amount, open_cost) = trade(CostBasisMethod::WeightedAverage);
+        assert_eq!(sell.cost_basis, 300);
+        assert_eq!(sell.pnl, 0);
+        assert_eq!((open_amount, open_cost), (50, 100));
+        // 持有时间不受加权平均合并 lot 的影响
+        assert_eq!(sell.holding_secs, (100 * 9 + 50 * 8) / 150);
+    }
+
+    #[test]
+    fn test_unmatched_sell_and_decimals() {
+        let mut book = LotBook::new(CostBasisMethod::Fifo);
+        // 同一个代币的数量精度不同时按更高的精度统一
+        book.buy(TokenAmount::new(1, 0), 1_000, 1);
+        book.buy(TokenAmount::new(5, 1), 1_000, 2);
+        assert_eq!(book.decimals, 1);
+        assert_eq!(book.open_amount(), 15);
+
+        // 卖出 3 个，只有 1.5 个有买入记录，收入按比例计入
+        let sell = book.sell(TokenAmount::new(3, 0), 6_000, 3);
+        assert_eq!(sell.matched, 15);
+        assert_eq!(sell.unmatched, 15);
+        assert_eq!(sell.proceeds, 3_000);
+        assert_eq!(sell.pnl, 1_000);
+        assert!(book.lots.is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/*
+ *  持仓时间与交易风格
+ *  每个代币的持仓时间由持仓批次把卖出匹配到之前的买入得到(按数量加权)，还没卖出的持仓按持有到现在计算，
+ *  按所有持仓时间的中位数把钱包分为狙击(秒级)、短线(分钟级)、波段(小时到天)与长期持有，
+ *  不同风格的钱包跟单时使用不同的策略，比如狙击钱包需要更快的卖出
+ */
+
+/// 钱包的交易风格
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraderStyle {
+    #[default]
+    Unknown, // 没有持仓记录
+    Sniper,  // 秒级，一般是开盘狙击或者机器人
+    Scalper, // 分钟级
+    Swing,   // 小时到天
+    Holder,  // 长期持有
+}
+
+impl TraderStyle {
+    /// 按持仓时间的中位数分类
+    pub fn classify(median_secs: u64, thresholds: &StyleThresholds) -> Self {
+        if median_secs < thresholds.sniper_secs {
+            TraderStyle::Sniper
+        } else if median_secs < thresholds.scalper_secs {
+            TraderStyle::Scalper
+        } else if median_secs < thresholds.swing_secs {
+            TraderStyle::Swing
+        } else {
+            TraderStyle::Holder
+        }
+    }
+}
+
+impl Display for TraderStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let style = match self {
+            TraderStyle::Unknown => "unknown",
+            TraderStyle::Sniper => "sniper",
+            TraderStyle::Scalper => "scalper",
+            TraderStyle::Swing => "swing",
+            TraderStyle::Holder => "holder",
+        };
+        write!(f, "{}", style)
+    }
+}
+
+/// 交易风格的分界(秒)，持仓时间小于分界时属于对应的风格
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StyleThresholds {
+    pub sniper_secs: u64,  // 小于该值为狙击
+    pub scalper_secs: u64, // 小于该值为短线
+    pub swing_secs: u64,   // 小于该值为波段，否则为长期持有
+}
+
+impl Default for StyleThresholds {
+    fn default() -> Self {
+        Self {
+            sniper_secs: 60,           // 1 分钟
+            scalper_secs: 60 * 60,     // 1 小时
+            swing_secs: 7 * 24 * 3600, // 7 天
+        }
+    }
+}
+
+/// 一个钱包的持仓时间汇总
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct HoldingProfile {
+    pub samples: usize,   // 持仓时间的样本数(已平仓的卖出 + 还没卖出的持仓)
+    pub median_secs: u64, // 持仓时间的中位数
+    pub style: TraderStyle,
+}
+
+impl HoldingProfile {
+    /// 由所有代币的持仓时间得出
+    pub fn from_durations(mut durations: Vec<u64>, thresholds: &StyleThresholds) -> Self {
+        if durations.is_empty() {
+            return Self::default();
+        }
+        durations.sort_unstable();
+        let mid = durations.len() / 2;
+        let median_secs = match durations.len() % 2 {
+            0 => (durations[mid - 1] + durations[mid]) / 2,
+            _ => durations[mid],
+        };
+        Self {
+            samples: durations.len(),
+            median_secs,
+            style: TraderStyle::classify(median_secs, thresholds),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_holding_profile() {
+        let thresholds = StyleThresholds::default();
+        assert_eq!(
+            HoldingProfile::from_durations(vec![], &thresholds).style,
+            TraderStyle::Unknown
+        );
+
+        let profile = HoldingProfile::from_durations(vec![3, 600, 5, 20], &thresholds);
+        assert_eq!(profile.samples, 4);
+        assert_eq!(profile.median_secs, 12);
+        assert_eq!(profile.style, TraderStyle::Sniper);
+
+        for (secs, style) in [
+            (59, TraderStyle::Sniper),
+            (60, TraderStyle::Scalper),
+            (2 * 3600, TraderStyle::Swing),
+            (30 * 24 * 3600, TraderStyle::Holder),
+        ] {
+            assert_eq!(TraderStyle::classify(secs, &thresholds), style);
+        }
+    }
+}
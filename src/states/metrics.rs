@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use crate::constant::SECS_PER_DAY;
+use crate::states::states::{ClosedTrade, User};
+
+/*
+ *  风险调整之后的收益指标
+ *  只统计已平仓的交易(有买入记录的卖出)，盈亏单位为 sol：
+ *  1.胜率(按交易与按代币)、盈亏比、平均盈利/亏损、每笔交易的期望收益
+ *  2.已实现盈亏曲线的最大回撤
+ *  3.按天汇总收益率(当天盈亏 / 当天平仓的成本，没有交易的日子为 0)之后的 Sharpe 与 Sortino
+ *  一次偶然的百倍收益会让总盈利很好看，但是胜率、期望收益与 Sharpe 能把它和稳定盈利的钱包区分开
+ */
+
+/// 一个钱包已平仓交易的收益指标
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct RiskMetrics {
+    pub trade_count: usize,         // 已平仓的交易笔数
+    pub token_count: usize,         // 有已平仓交易的代币数量
+    pub trade_win_rate: f64,        // 盈利的交易占比
+    pub token_win_rate: f64,        // 已实现盈亏为正的代币占比
+    pub profit_factor: Option<f64>, // 总盈利 / 总亏损，没有亏损时为 None
+    pub average_win: f64,           // 盈利交易的平均盈利
+    pub average_loss: f64,          // 亏损交易的平均亏损(正数)
+    pub expectancy: f64,            // 每笔交易的平均盈亏
+    pub max_drawdown: f64,          // 累计已实现盈亏从最高点回落的最大值
+    pub sharpe_ratio: Option<f64>,  // 日收益率的均值 / 标准差，少于两天时为 None
+    pub sortino_ratio: Option<f64>, // 日收益率的均值 / 下行标准差，没有亏损的日子时为 None
+}
+
+impl RiskMetrics {
+    /// 由钱包所有代币的已平仓交易计算
+    pub fn from_user(user: &User) -> Self {
+        let trades = user.closed_trades();
+        if trades.is_empty() {
+            return Self::default();
+        }
+
+        let pnls = trades.iter().map(|trade| trade.pnl);
+        let (gains, losses) = gains_and_losses(pnls.clone());
+        let wins = pnls.clone().filter(|pnl| *pnl > 0.0).count();
+        let loses = pnls.clone().filter(|pnl| *pnl < 0.0).count();
+
+        let token_profits: Vec<f64> = user
+            .token_stats
+            .values()
+            .filter(|stats| !stats.closed_trades.is_empty())
+            .map(|stats| stats.closed_trades.iter().map(|trade| trade.pnl).sum())
+            .collect();
+        let winning_tokens = token_profits.iter().filter(|pnl| **pnl > 0.0).count();
+
+        let returns = daily_returns(&trades);
+        Self {
+            trade_count: trades.len(),
+            token_count: token_profits.len(),
+            trade_win_rate: wins as f64 / trades.len() as f64,
+            token_win_rate: winning_tokens as f64 / token_profits.len() as f64,
+            profit_factor: (losses > 0.0).then(|| gains / losses),
+            average_win: average(gains, wins),
+            average_loss: average(losses, loses),
+            expectancy: (gains - losses) / trades.len() as f64,
+            max_drawdown: max_drawdown(&trades),
+            sharpe_ratio: sharpe_ratio(&returns),
+            sortino_ratio: sortino_ratio(&returns),
+        }
+    }
+}
+
+impl Display for RiskMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ratio = |value: Option<f64>| match value {
+            Some(value) => format!("{:.2}", value),
+            None => "-".to_string(),
+        };
+        write!(
+            f,
+            "closed trades: {}, tokens: {}, win rate: {:.2}% (per token {:.2}%), profit factor: {}, \
+             average win: {:.4}, average loss: {:.4}, expectancy: {:.4}, max drawdown: {:.4}, sharpe: {}, sortino: {}",
+            self.trade_count,
+            self.token_count,
+            self.trade_win_rate * 100.0,
+            self.token_win_rate * 100.0,
+            ratio(self.profit_factor),
+            self.average_win,
+            self.average_loss,
+            self.expectancy,
+            self.max_drawdown,
+            ratio(self.sharpe_ratio),
+            ratio(self.sortino_ratio),
+        )
+    }
+}
+
+/// 盈利的总和与亏损的总和(正数)
+pub fn gains_and_losses(pnls: impl Iterator<Item = f64>) -> (f64, f64) {
+    pnls.fold((0.0, 0.0), |(gains, losses), pnl| match pnl > 0.0 {
+        true => (gains + pnl, losses),
+        false => (gains, losses - pnl),
+    })
+}
+
+/// 按时间顺序累计已实现盈亏，返回从最高点回落的最大值
+pub fn max_drawdown(trades: &[ClosedTrade]) -> f64 {
+    let mut cumulative = 0.0;
+    let mut peak = 0.0f64;
+    let mut drawdown = 0.0f64;
+    for trade in trades {
+        cumulative += trade.pnl;
+        peak = peak.max(cumulative);
+        drawdown = drawdown.max(peak - cumulative);
+    }
+    drawdown
+}
+
+/// 从第一笔到最后一笔交易的每一天的收益率(当天盈亏 / 当天平仓的成本)，没有交易的日子为 0
+pub fn daily_returns(trades: &[ClosedTrade]) -> Vec<f64> {
+    let mut buckets: BTreeMap<u64, (f64, f64)> = BTreeMap::new();
+    for trade in trades {
+        let bucket = buckets
+            .entry(trade.timestamp / SECS_PER_DAY)
+            .or_insert((0.0, 0.0));
+        bucket.0 += trade.pnl;
+        bucket.1 += trade.cost;
+    }
+    let (Some(first), Some(last)) = (buckets.keys().next(), buckets.keys().next_back()) else {
+        return vec![];
+    };
+    (*first..=*last)
+        .map(|day| match buckets.get(&day) {
+            Some((pnl, cost)) if *cost > 0.0 => pnl / cost,
+            _ => 0.0,
+        })
+        .collect()
+}
+
+// 收益率的均值 / 标准差(样本)
+fn sharpe_ratio(returns: &[f64]) -> Option<f64> {
+    if returns.len() < 2 {
+        return None;
+    }
+    let mean = mean(returns);
+    let variance = returns
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / (returns.len() - 1) as f64;
+    let deviation = variance.sqrt();
+    (deviation > 0.0).then(|| mean / deviation)
+}
+
+// 收益率的均值 / 下行标准差(只统计负收益)
+fn sortino_ratio(returns: &[f64]) -> Option<f64> {
+    if returns.len() < 2 {
+        return None;
+    }
+    let downside = returns
+        .iter()
+        .map(|value| value.min(0.0).powi(2))
+        .sum::<f64>()
+        / returns.len() as f64;
+    let deviation = downside.sqrt();
+    (deviation > 0.0).then(|| mean(returns) / deviation)
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn average(total: f64, count: usize) -> f64 {
+    match count {
+        0 => 0.0,
+        _ => total / count as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(day: u64, cost: f64, pnl: f64) -> ClosedTrade {
+        ClosedTrade {
+            timestamp: day * SECS_PER_DAY + 60,
+            cost,
+            pnl,
+            holding_secs: 60,
+        }
+    }
+
+    #[test]
+    fn test_drawdown_and_daily_returns() {
+        // 第 0 天赚 1，第 1 天亏 1.5，第 3 天赚 0.5
+        let trades = vec![trade(0, 1.0, 1.0), trade(1, 3.0, -1.5), trade(3, 1.0, 0.5)];
+        assert_eq!(max_drawdown(&trades), 1.5);
+        assert_eq!(gains_and_losses(trades.iter().map(|t| t.pnl)), (1.5, 1.5));
+
+        let returns = daily_returns(&trades);
+        assert_eq!(returns, vec![1.0, -0.5, 0.0, 0.5]);
+        assert!((sharpe_ratio(&returns).unwrap() - 0.25 / 0.6454972243679028).abs() < 1e-9);
+        assert!((sortino_ratio(&returns).unwrap() - 0.25 / 0.25).abs() < 1e-9);
+        // 只有一天时没有意义
+        assert_eq!(sharpe_ratio(&returns[..1]), None);
+    }
+}
@@ -1 +1,7 @@
+pub mod activity;
+pub mod cost_basis;
+pub mod holding;
+pub mod metrics;
 pub mod states;
+pub mod swap_event;
+pub mod window;
@@ -1,11 +1,26 @@
+use crate::constant::SECS_PER_DAY;
+use crate::states::cost_basis::{CostBasisMethod, LotBook, RealizedSell};
+use crate::states::holding::{HoldingProfile, StyleThresholds, TraderStyle};
+use crate::states::metrics::RiskMetrics;
+use crate::states::swap_event::{SwapEvent, TokenAmount, TradeSide};
+use crate::states::window::WindowSnapshot;
+use crate::utils::analyze_utils::{SolChange, TradeCosts};
+use crate::utils::scoring::{ScoreCard, Scorer, WeightedScorer};
+use crate::utils::swap_decoder::SOL_DECIMALS;
 use serde::{Deserialize, Serialize};
 use solana_program::pubkey::Pubkey;
-use solana_sdk::{
-    message::v0::MessageAddressTableLookup, signer::Signer, transaction::VersionedTransaction,
-};
 use std::collections::HashMap;
 use std::fmt::Formatter;
 
+/// 一次有买入记录的卖出(已平仓的交易)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClosedTrade {
+    pub timestamp: u64,    // 卖出时间
+    pub cost: f64,         // 匹配部分的买入成本(sol)
+    pub pnl: f64,          // 已实现盈亏(sol)
+    pub holding_secs: u64, // 按数量加权的持有时间(秒)
+}
+
 // 某个钱包与持有代币的相关信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenTradeStats {
@@ -19,14 +34,23 @@ pub struct TokenTradeStats {
     pub bought_time: Vec<u64>, // 买入时间
     pub sold_time: Vec<u64>,   // 卖出时间
 
-    pub profit: f64,     // 盈亏（可能是价格差累积计算）
-    pub win_count: u32,  // 盈利交易笔数
-    pub lose_count: u32, // 亏损交易笔数
+    pub profit: f64,     // 已实现盈亏(以计价代币计，一般为 sol)，按持仓批次计算
+    pub win_count: u32,  // 盈利的卖出笔数
+    pub lose_count: u32, // 亏损的卖出笔数
+
+    pub lots: LotBook,                   // 买入形成的持仓批次，卖出时从中扣除成本
+    pub unrealized_pnl: f64,             // 剩余持仓按当前价格估值的未实现盈亏(sol)
+    pub closed_trades: Vec<ClosedTrade>, // 有买入记录的卖出，按时间顺序
 }
 
 impl TokenTradeStats {
-    /// 创建一个新的 TokenTradeStats，初始值全为 0 或空
+    /// 创建一个新的 TokenTradeStats，初始值全为 0 或空，按 FIFO 计算成本
     pub fn new(token_mint: Pubkey) -> Self {
+        Self::with_method(token_mint, CostBasisMethod::Fifo)
+    }
+
+    /// 使用指定的成本计算方式创建 TokenTradeStats
+    pub fn with_method(token_mint: Pubkey, method: CostBasisMethod) -> Self {
         Self {
             symbol: "".to_string(),
             token_mint,
@@ -38,6 +62,9 @@ impl TokenTradeStats {
             profit: 0.0,
             win_count: 0,
             lose_count: 0,
+            lots: LotBook::new(method),
+            unrealized_pnl: 0.0,
+            closed_trades: vec![],
         }
     }
 
@@ -50,6 +77,77 @@ impl TokenTradeStats {
         self.bought_time.push(timestamp);
     }
 
+    /// 记录一次 swap 事件，卖出时返回按持仓批次结算的结果
+    /// - 买入形成新的持仓批次，卖出的盈亏 = 卖出收入 - 按成本计算方式扣除的买入成本
+    /// - 超出持仓的卖出(买入不在统计范围内)不计算盈亏，只有匹配部分计入胜负
+    pub fn record_swap(&mut self, event: &SwapEvent) -> Option<RealizedSell> {
+        let amount = event.token_amount.ui_amount();
+        match event.side {
+            TradeSide::Buy => {
+                self.lots.buy(
+                    event.token_amount,
+                    event.quote_amount.amount,
+                    event.block_time,
+                );
+                self.record_buy(amount, event.block_time);
+                None
+            }
+            TradeSide::Sell => {
+                let realized = self.lots.sell(
+                    event.token_amount,
+                    event.quote_amount.amount,
+                    event.block_time,
+                );
+                let decimals = event.quote_amount.decimals;
+                let profit = quote_ui_amount(realized.pnl, decimals);
+                self.record_sell(amount, event.block_time, profit);
+
+                // 判断盈亏记录
+                if realized.matched > 0 {
+                    self.closed_trades.push(ClosedTrade {
+                        timestamp: event.block_time,
+                        cost: quote_ui_amount(realized.cost_basis as i128, decimals),
+                        pnl: profit,
+                        holding_secs: realized.holding_secs,
+                    });
+                    if realized.pnl > 0 {
+                        self.win_count += 1;
+                    } else if realized.pnl < 0 {
+                        self.lose_count += 1;
+                    }
+                }
+                Some(realized)
+            }
+        }
+    }
+
+    /// 该代币每次持仓的时间(秒)
+    /// - 已卖出的部分按 FIFO 匹配到之前每一次买入的时间，与成本计算方式无关
+    /// - 还没卖出的每次买入按持有到 `now` 计算
+    pub fn holding_durations(&self, now: u64) -> Vec<u64> {
+        let closed = self.closed_trades.iter().map(|trade| trade.holding_secs);
+        let open = self
+            .lots
+            .open_buys
+            .iter()
+            .map(|buy| now.saturating_sub(buy.timestamp));
+        closed.chain(open).collect()
+    }
+
+    /// 剩余持仓的买入成本(sol)
+    pub fn open_cost(&self) -> f64 {
+        quote_ui_amount(self.lots.open_cost() as i128, SOL_DECIMALS)
+    }
+
+    /// 按当前价格给剩余持仓估值，返回未实现盈亏(sol)
+    /// - `price` 每个代币值多少 sol
+    pub fn mark_to_market(&mut self, price: f64) -> f64 {
+        let decimals = self.lots.decimals;
+        let open_amount = TokenAmount::new(self.lots.open_amount(), decimals).ui_amount();
+        self.unrealized_pnl = open_amount * price - self.open_cost();
+        self.unrealized_pnl
+    }
+
     /// 记录一次卖出操作
     /// - `amount` 卖出数量
     /// - `timestamp` 发生时间
@@ -64,6 +162,15 @@ impl TokenTradeStats {
     }
 }
 
+// 计价代币最小单位的数量换算成 ui 数量(可以为负)
+fn quote_ui_amount(amount: i128, decimals: u8) -> f64 {
+    let ui_amount = TokenAmount::new(amount.unsigned_abs(), decimals).ui_amount();
+    match amount < 0 {
+        true => -ui_amount,
+        false => ui_amount,
+    }
+}
+
 impl std::fmt::Display for TokenTradeStats {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -89,16 +196,29 @@ pub struct User {
     pub address: Pubkey,                               // 用户地址
     pub history_txs: Vec<String>,                      // 这段时间内用户所有的交易
     pub token_txs: Vec<String>,                        // 这段时间内与代币相关交易签名列表
+    pub swap_events: Vec<SwapEvent>,                   // 这段时间内的所有买卖事件
     pub token_stats: HashMap<Pubkey, TokenTradeStats>, // 我们对代币的相关信息不停留在交易上，而关注这个钱包在一段时间内对某个代币的买卖
-    pub distinct_token_count: u8,                      // 当前账户一段时间内的买卖代币总数
+    pub distinct_token_count: usize,                   // 当前账户一段时间内的买卖代币总数
     pub time_day: u8,                                  // 时间期限, 以“天”为单位
 
-    pub total_cost: f64,   // 这段时间内总的成本
-    pub total_profit: f64, // 这段时间内总的盈利
+    pub total_cost: f64,   // 这段时间内已卖出部分的买入成本(sol)，由持仓批次得出
+    pub total_profit: f64, // 这段时间内的已实现盈亏(sol)，由持仓批次得出
+
+    pub open_cost: f64,      // 剩余持仓的买入成本(sol)，包括拿不到价格的持仓
+    pub unrealized_pnl: f64, // 按市价估值的剩余持仓的未实现盈亏(sol)
+    pub unpriced_mints: Vec<Pubkey>, // 拿不到价格的剩余持仓(池子关闭、被 rug 或者没有价格来源)，没有未实现盈亏
 
-    // 一个百分数，判断该账户在当前时间段余额的变化(总的成本/总的盈利)
+    // 一个百分数，判断该账户在当前时间段余额的变化((已实现 + 未实现盈亏)/(已卖出 + 剩余持仓的成本))
     pub balance_change: f64,
 
+    pub sol_pnl: i64,            // 买卖代币本身的 sol 收支(lamports，不含成本)
+    pub trade_costs: TradeCosts, // 链上交易的手续费/优先费/小费/租金
+    pub latest_slot: u64,        // 已分析交易中最新的 slot，用于写回地址库
+    pub cost_basis_method: CostBasisMethod, // 卖出时扣除成本的方式
+    pub holding: HoldingProfile, // 持仓时间与交易风格，用于选择跟单策略
+    pub metrics: RiskMetrics,    // 已平仓交易的胜率/盈亏比/回撤/Sharpe 等指标
+    pub windows: Vec<WindowSnapshot>, // 最近 24h/7d/30d 等滚动窗口内的统计，用于判断优势是否在减弱
+
     pub score: f64, // 最终评分
 }
 
@@ -109,16 +229,33 @@ impl User {
             address,
             history_txs: vec![],
             token_txs: vec![],
+            swap_events: vec![],
             token_stats: HashMap::new(),
             distinct_token_count: 0,
             time_day,
             total_cost: 0.0,
             total_profit: 0.0,
+            open_cost: 0.0,
+            unrealized_pnl: 0.0,
+            unpriced_mints: vec![],
             balance_change: 0.0,
+            sol_pnl: 0,
+            trade_costs: TradeCosts::default(),
+            latest_slot: 0,
+            cost_basis_method: CostBasisMethod::Fifo,
+            holding: HoldingProfile::default(),
+            metrics: RiskMetrics::default(),
+            windows: vec![],
             score: 0.0,
         }
     }
 
+    /// 时间窗口的起点，`time_day` 为 0 时不限制
+    /// - `now` 当前的 unix 时间戳(秒)
+    pub fn window_start(&self, now: u64) -> Option<u64> {
+        (self.time_day > 0).then(|| now.saturating_sub(self.time_day as u64 * SECS_PER_DAY))
+    }
+
     /// 添加一条与代币相关的交易签名到历史列表
     pub fn add_history_tx(&mut self, signature: &str) {
         self.history_txs.push(signature.parse().unwrap());
@@ -170,34 +307,127 @@ impl User {
         self.distinct_token_count += 1;
     }
 
-    // 统计余额增长百分比
+    /// 记录一次买卖事件(链上解析、数据源、fixture 都统一成 SwapEvent)
+    /// - 卖出时返回按持仓批次结算的结果
+    /// - 金额是估算出来的事件(`estimated`)只记录事件本身，不进入持仓批次与盈亏
+    pub fn record_swap_event(&mut self, event: &SwapEvent) -> Option<RealizedSell> {
+        if !self.token_stats.contains_key(&event.mint) {
+            self.distinct_token_count += 1;
+        }
+        let entry = self
+            .token_stats
+            .entry(event.mint)
+            .or_insert_with(|| TokenTradeStats::with_method(event.mint, self.cost_basis_method));
+        if event.estimated {
+            self.swap_events.push(event.clone());
+            return None;
+        }
+
+        // 卖出时累计匹配部分的买入成本与已实现盈亏
+        let realized = entry.record_swap(event);
+        if let Some(realized) = realized {
+            let decimals = event.quote_amount.decimals;
+            self.total_cost += quote_ui_amount(realized.cost_basis as i128, decimals);
+            self.total_profit += quote_ui_amount(realized.pnl, decimals);
+        }
+        self.swap_events.push(event.clone());
+        realized
+    }
+
+    /// 按时间顺序记录一组买卖事件，数据源返回的顺序可能是从新到旧的
+    pub fn record_swap_events(&mut self, mut events: Vec<SwapEvent>) {
+        events.sort_by_key(|event| (event.block_time, event.slot));
+        for event in &events {
+            self.record_swap_event(event);
+        }
+    }
+
+    /// 记录一笔链上交易的 sol 变化，盈亏只按 swap 本身计算，成本单独累计
+    pub fn record_sol_change(&mut self, sol_change: &SolChange) {
+        self.sol_pnl += sol_change.swap_notional;
+        self.trade_costs.add(&sol_change.costs);
+    }
+
+    /// 按当前价格给所有剩余持仓估值，汇总未实现盈亏
+    /// - `prices` 代币 mint => 每个代币值多少 sol
+    /// - 没有价格的持仓同样计入 `open_cost`，mint 记录在 `unpriced_mints` 中
+    pub fn mark_to_market(&mut self, prices: &HashMap<Pubkey, f64>) {
+        self.open_cost = 0.0;
+        self.unrealized_pnl = 0.0;
+        self.unpriced_mints.clear();
+        for (mint, stats) in self.token_stats.iter_mut() {
+            if stats.lots.open_amount() == 0 {
+                continue;
+            }
+            self.open_cost += stats.open_cost();
+            match prices.get(mint) {
+                Some(price) => self.unrealized_pnl += stats.mark_to_market(*price),
+                None => self.unpriced_mints.push(*mint),
+            }
+        }
+        self.unpriced_mints.sort();
+    }
+
+    // 统计余额增长百分比，包括剩余持仓的未实现盈亏
     pub fn count_balance_change(&mut self) {
-        self.balance_change = self.total_profit / self.total_cost;
+        let cost = self.total_cost + self.open_cost;
+        if cost <= 0.0 {
+            self.balance_change = 0.0;
+            return;
+        }
+        self.balance_change = (self.total_profit + self.unrealized_pnl) / cost;
     }
 
-    /// 根据当前账户运营 token 的能力来打分
-    /// 返回一个 f64，表示综合分值
-    // todo!: 对于一个打分函数而言，其实我觉得还应该考虑以下几个方面：1.账户的胜率(针对到每一个代币) 2.持仓时常/周转率(这可以反映一个账户是擅长短线还是长线，对于短线和长线我们可以有不同的跟单策略) 3.单个token的盈亏(对如说用户在一个代币上赚了很多钱，而在另一个代币上亏了很多钱)
-    pub fn score(&mut self) {
-        // 1. 先定义各项权重
-        // 根据业务需求做调优，比如更重视余额涨幅还是更重视交易活跃度
-        let weight_distinct_tokens = 0.3; // 持有不同代币数权重
-        let weight_token_txs = 0.3; // 代币相关交易次数权重
-        let weight_balance_change = 0.4; // 余额变化百分比权重
+    /// 所有代币的已平仓交易，按卖出时间排序
+    pub fn closed_trades(&self) -> Vec<ClosedTrade> {
+        let mut trades: Vec<ClosedTrade> = self
+            .token_stats
+            .values()
+            .flat_map(|stats| stats.closed_trades.iter().cloned())
+            .collect();
+        trades.sort_by_key(|trade| trade.timestamp);
+        trades
+    }
+
+    /// 按所有代币的持仓时间判断交易风格，结果写入 `holding`
+    /// - `now` 当前的 unix 时间戳，还没卖出的持仓按持有到现在计算
+    pub fn classify_style(&mut self, thresholds: &StyleThresholds, now: u64) -> TraderStyle {
+        let durations = self
+            .token_stats
+            .values()
+            .flat_map(|stats| stats.holding_durations(now))
+            .collect();
+        self.holding = HoldingProfile::from_durations(durations, thresholds);
+        self.holding.style
+    }
+
+    /// 计算已平仓交易的收益指标，结果写入 `metrics`
+    pub fn compute_metrics(&mut self) -> &RiskMetrics {
+        self.metrics = RiskMetrics::from_user(self);
+        &self.metrics
+    }
 
-        // 2. 计算各项“子得分”
-        let distinct_token_score = self.distinct_token_count as f64;
-        let token_txs_score = self.token_txs.len() as f64;
-        let balance_change_score = self.balance_change * 100.0;
-        // 把 [0.15 => 15%] 转成 15.0 这样好做加权
+    /// 分别统计多个滚动窗口内的买卖，结果写入 `windows`
+    /// - `windows` 窗口长度(秒)，比如 `DEFAULT_WINDOWS`
+    /// - `now` 窗口的终点(unix 时间戳)
+    pub fn compute_windows(&mut self, windows: &[u64], now: u64) -> &[WindowSnapshot] {
+        self.windows = windows
+            .iter()
+            .map(|window_secs| WindowSnapshot::from_user(self, *window_secs, now))
+            .collect();
+        &self.windows
+    }
 
-        // 3. 组合加权
-        let total_score = weight_distinct_tokens * distinct_token_score
-            + weight_token_txs * token_txs_score
-            + weight_balance_change * balance_change_score;
+    /// 使用默认的多因子配置打分，结果(0 ~ 100)写入 `score`
+    pub fn score(&mut self) {
+        self.score_with(&WeightedScorer::default());
+    }
 
-        // 这里直接返回总分即可
-        self.score = total_score
+    /// 使用指定的评分器打分，返回各个因子的得分
+    pub fn score_with(&mut self, scorer: &dyn Scorer) -> ScoreCard {
+        let card = scorer.score(self);
+        self.score = card.total;
+        card
     }
 
     /// 输出当前用户的汇总信息
@@ -230,10 +460,30 @@ impl std::fmt::Display for User {
         ).expect("TODO: panic message");
         write!(
             f,
-            "\nthe number of tokens held by user:{},\ntotal cost: {},\ntotal profit:{}",
-            self.distinct_token_count, self.total_cost, self.total_profit
+            "\nthe number of tokens held by user:{},\ntotal cost: {},\ntotal profit:{},\nunrealized pnl: {}",
+            self.distinct_token_count, self.total_cost, self.total_profit, self.unrealized_pnl
+        )
+        .expect("TODO: panic message");
+        write!(
+            f,
+            "\nsol pnl(lamports): {}, \ntrade costs(lamports): fee {}, priority fee {}, tip {}, rent {}",
+            self.sol_pnl,
+            self.trade_costs.network_fee,
+            self.trade_costs.priority_fee,
+            self.trade_costs.tip,
+            self.trade_costs.rent
         )
         .expect("TODO: panic message");
+        write!(
+            f,
+            "\ntrading style: {}, median holding time: {}s",
+            self.holding.style, self.holding.median_secs
+        )
+        .expect("TODO: panic message");
+        write!(f, "\nrisk metrics: {}", self.metrics).expect("TODO: panic message");
+        for window in &self.windows {
+            write!(f, "\n{}", window).expect("TODO: panic message");
+        }
         write!(
             f,
             "\nuser's balance change: {:.2}%",
@@ -245,6 +495,127 @@ impl std::fmt::Display for User {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::states::swap_event::test_support::swap;
+
+    #[test]
+    fn test_mark_to_market() {
+        let mint = Pubkey::new_unique();
+        let mut user = User::new(Pubkey::new_from_array([1; 32]), 7);
+        // 1 sol 买入 1,000,000 个，1 sol 卖出一半，之后价格跌了 90%
+        user.record_swap_events(vec![
+            swap(mint, TradeSide::Sell, 500_000_000_000, 1_000_000_000, 2),
+            swap(mint, TradeSide::Buy, 1_000_000_000_000, 1_000_000_000, 1),
+        ]);
+        assert_eq!(user.total_profit, 0.5);
+
+        user.mark_to_market(&HashMap::from([(mint, 0.0000001)]));
+        assert_eq!(user.open_cost, 0.5);
+        assert!((user.unrealized_pnl + 0.45).abs() < 1e-9);
+        user.count_balance_change();
+        assert!((user.balance_change - 0.05).abs() < 1e-9);
+
+        // 拿不到价格的持仓计入成本，并且单独记录
+        let rugged = Pubkey::new_unique();
+        user.record_swap_event(&swap(rugged, TradeSide::Buy, 1_000, 500_000_000, 3));
+        user.mark_to_market(&HashMap::from([(mint, 0.0000001)]));
+        assert_eq!(user.open_cost, 1.0);
+        assert_eq!(user.unpriced_mints, vec![rugged]);
+        assert!((user.unrealized_pnl + 0.45).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_classify_style() {
+        let (fast, slow) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut user = User::new(Pubkey::new_from_array([1; 32]), 7);
+        // fast 买入 10 秒之后卖出一半，slow 最后才买入，
+        // 现在(7360)的持仓时间为 [10, 7260, 60]
+        user.record_swap_events(vec![
+            swap(fast, TradeSide::Buy, 1_000, 1_000_000_000, 100),
+            swap(fast, TradeSide::Sell, 500, 600_000_000, 110),
+            swap(slow, TradeSide::Buy, 1_000, 1_000_000_000, 7_300),
+        ]);
+        let now = 7_360;
+        let durations = user.token_stats[&fast].holding_durations(now);
+        assert_eq!(durations, vec![10, 7_260]);
+        let style = user.classify_style(&StyleThresholds::default(), now);
+        assert_eq!(style, TraderStyle::Scalper);
+        assert_eq!(user.holding.samples, 3);
+        assert_eq!(user.holding.median_secs, 60);
+
+        // 加权平均成本时，补仓不会把之前买入的持有时间重置
+        let mut average = User::new(Pubkey::new_from_array([1; 32]), 7);
+        average.cost_basis_method = CostBasisMethod::WeightedAverage;
+        average.record_swap_events(vec![
+            swap(fast, TradeSide::Buy, 1_000, 1_000_000_000, 100),
+            swap(fast, TradeSide::Buy, 1_000, 1_000_000_000, 7_300),
+            swap(fast, TradeSide::Sell, 1_000, 1_000_000_000, 7_301),
+        ]);
+        let durations = average.token_stats[&fast].holding_durations(now);
+        assert_eq!(durations, vec![7_201, 60]);
+    }
+
+    #[test]
+    fn test_compute_metrics() {
+        let (fast, slow) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut user = User::new(Pubkey::new_from_array([1; 32]), 7);
+        user.record_swap_events(vec![
+            swap(fast, TradeSide::Buy, 1_000, 1_000_000_000, 100),
+            swap(fast, TradeSide::Sell, 500, 600_000_000, 110),
+            swap(slow, TradeSide::Buy, 1_000, 1_000_000_000, 7_300),
+        ]);
+
+        // 只有 fast 卖出的一半平仓: 0.6 - 0.5，slow 还没有卖出
+        let metrics = *user.compute_metrics();
+        assert_eq!((metrics.trade_count, metrics.token_count), (1, 1));
+        assert_eq!(metrics.token_win_rate, 1.0);
+        assert!((metrics.expectancy - 0.1).abs() < 1e-9);
+        assert_eq!(metrics.profit_factor, None);
+    }
+
+    #[test]
+    fn test_rolling_windows() {
+        let mint = Pubkey::new_unique();
+        let now = 40 * SECS_PER_DAY;
+        let mut user = User::new(Pubkey::new_from_array([1; 32]), 30);
+        assert_eq!(user.window_start(now), Some(10 * SECS_PER_DAY));
+        // 20 天前赚了 1 sol，昨天之内亏了 0.5 sol
+        user.record_swap_events(vec![
+            swap(
+                mint,
+                TradeSide::Buy,
+                1_000,
+                1_000_000_000,
+                now - 20 * SECS_PER_DAY,
+            ),
+            swap(
+                mint,
+                TradeSide::Sell,
+                1_000,
+                2_000_000_000,
+                now - 19 * SECS_PER_DAY,
+            ),
+            swap(mint, TradeSide::Buy, 1_000, 1_000_000_000, now - 3600),
+            swap(mint, TradeSide::Sell, 1_000, 500_000_000, now - 60),
+        ]);
+
+        let windows = user.compute_windows(&crate::states::window::DEFAULT_WINDOWS, now);
+        let rois: Vec<f64> = windows.iter().map(|window| window.roi).collect();
+        assert_eq!(rois, vec![-0.5, -0.5, 0.25]);
+        assert_eq!(windows[2].swap_count, 4);
+        assert_eq!(windows[2].metrics.trade_win_rate, 0.5);
+        assert!(user.to_string().contains("[1d] swaps: 2"));
+    }
+
+    #[test]
+    fn test_distinct_token_count() {
+        // 活跃钱包一段时间内买卖的代币数量可能超过 255
+        let mut user = User::new(Pubkey::new_unique(), 7);
+        for time in 0..300 {
+            user.record_swap_event(&swap(Pubkey::new_unique(), TradeSide::Buy, 1, 1, time));
+        }
+        assert_eq!(user.distinct_token_count, 300);
+    }
+
     #[test]
     fn test_display_user() {
         let address: Pubkey = Pubkey::new_from_array([1; 32]);
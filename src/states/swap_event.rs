@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use solana_program::pubkey::Pubkey;
+use std::path::Path;
+
+/*
+ *  统一的 swap 事件
+ *  链上解析(pump.fun/raydium)、钱包活动数据源、测试用的 fixture 都转换成 SwapEvent，
+ *  TokenTradeStats 与 User 只处理 SwapEvent
+ */
+
+/// 买卖方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+/// 交易发生的场所
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Venue {
+    PumpFun,
+    RaydiumAmmV4,
+    Unknown, // 数据源没有给出具体场所
+}
+
+/// 精确的代币数量(最小单位 + 精度)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TokenAmount {
+    pub amount: u128, // 最小单位的数量
+    pub decimals: u8, // 精度
+}
+
+impl TokenAmount {
+    pub fn new(amount: u128, decimals: u8) -> Self {
+        Self { amount, decimals }
+    }
+
+    /// 按精度换算成 ui 数量
+    pub fn ui_amount(&self) -> f64 {
+        self.amount as f64 / 10f64.powi(self.decimals as i32)
+    }
+
+    /// 精确解析十进制字符串，比如 "5871603.62937500000000000000"
+    /// - `decimals` 为 None 时精度取小数部分去掉末尾 0 之后的位数
+    /// - 小数位数超过给定精度(去掉末尾 0 之后)或者格式不对时返回 None
+    pub fn parse_decimal(value: &str, decimals: Option<u8>) -> Option<Self> {
+        let (integer, fraction) = value.trim().split_once('.').unwrap_or((value.trim(), ""));
+        let fraction = fraction.trim_end_matches('0');
+        let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if integer.is_empty() || !all_digits(integer) || !all_digits(fraction) {
+            return None;
+        }
+
+        let decimals = match decimals {
+            Some(decimals) if fraction.len() > decimals as usize => return None,
+            Some(decimals) => decimals,
+            None => u8::try_from(fraction.len()).ok()?,
+        };
+        let padded = format!(
+            "{}{:0<width$}",
+            integer,
+            fraction,
+            width = decimals as usize
+        );
+        Some(Self {
+            amount: padded.parse::<u128>().ok()?,
+            decimals,
+        })
+    }
+}
+
+/// 一次买卖代币的行为
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwapEvent {
+    #[serde(with = "pubkey_string")]
+    pub wallet: Pubkey, // 交易的钱包
+    #[serde(with = "pubkey_string")]
+    pub mint: Pubkey, // 买卖的代币
+    pub side: TradeSide,
+    pub token_amount: TokenAmount, // 买卖的代币数量
+    pub quote_amount: TokenAmount, // 付出/收到的计价代币数量
+    #[serde(with = "pubkey_string")]
+    pub quote_mint: Pubkey, // 计价代币(一般为 wsol)
+    pub price: f64,                // 每个代币的价格(以计价代币计)
+    pub slot: Option<u64>,         // 数据源不提供 slot 时为 None
+    pub block_time: u64,           // 交易时间戳
+    pub venue: Venue,
+    pub signature: String,
+    #[serde(default, with = "pubkey_string_vec")]
+    pub route: Vec<Pubkey>, // 链上解析出的调用路径(聚合器 -> pump/raydium)
+
+    #[serde(default)]
+    pub value_usd: Option<f64>, // 本次交易的 usd 价值(数据源提供时才有)
+    #[serde(default)]
+    pub cost_basis_usd: Option<f64>, // 卖出时数据源提供的买入成本(usd)
+    #[serde(default)]
+    pub estimated: bool, // sol 数量取自指令限额(max_sol_cost/min_sol_output)而不是真实收支，不计入盈亏
+}
+
+impl SwapEvent {
+    /// 根据数量计算价格
+    pub fn compute_price(token_amount: &TokenAmount, quote_amount: &TokenAmount) -> f64 {
+        let token = token_amount.ui_amount();
+        if token == 0.0 {
+            0.0
+        } else {
+            quote_amount.ui_amount() / token
+        }
+    }
+}
+
+/// 从 json fixture 文件中读取 swap 事件
+pub fn load_swap_events(path: &Path) -> anyhow::Result<Vec<SwapEvent>> {
+    let data = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&data)?)
+}
+
+// 地址在 json 中以 base58 字符串表示
+mod pubkey_string {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use solana_program::pubkey::Pubkey;
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(key: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(key)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Pubkey::from_str(&value).map_err(de::Error::custom)
+    }
+}
+
+mod pubkey_string_vec {
+    use serde::{de, ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+    use solana_program::pubkey::Pubkey;
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(keys: &[Pubkey], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(keys.len()))?;
+        for key in keys {
+            seq.serialize_element(&key.to_string())?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Pubkey>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|value| Pubkey::from_str(value).map_err(de::Error::custom))
+            .collect()
+    }
+}
+
+/// 测试中构造 SwapEvent
+#[cfg(test)]
+pub mod test_support {
+    use super::*;
+    use crate::constant::WSOL_MINT_PUBKEY;
+
+    /// pump.fun 上用 sol 买卖 6 位小数代币，签名为时间戳
+    /// - `tokens` 代币数量(最小单位)
+    /// - `lamports` 付出/收到的 lamports
+    pub fn swap(
+        mint: Pubkey,
+        side: TradeSide,
+        tokens: u128,
+        lamports: u128,
+        time: u64,
+    ) -> SwapEvent {
+        let token_amount = TokenAmount::new(tokens, 6);
+        let quote_amount = TokenAmount::new(lamports, 9);
+        SwapEvent {
+            wallet: Pubkey::new_from_array([1; 32]),
+            mint,
+            side,
+            price: SwapEvent::compute_price(&token_amount, &quote_amount),
+            token_amount,
+            quote_amount,
+            quote_mint: WSOL_MINT_PUBKEY,
+            slot: None,
+            block_time: time,
+            venue: Venue::PumpFun,
+            signature: time.to_string(),
+            route: vec![],
+            value_usd: None,
+            cost_basis_usd: None,
+            estimated: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_decimal() {
+        let amount = TokenAmount::parse_decimal("5871603.62937500000000000000", None).unwrap();
+        assert_eq!(amount, TokenAmount::new(5871603629375, 6));
+
+        let quote = TokenAmount::parse_decimal("9.90099009900000000000", Some(9)).unwrap();
+        assert_eq!(quote, TokenAmount::new(9_900_990_099, 9));
+        assert_eq!(
+            TokenAmount::parse_decimal("12", Some(6)),
+            Some(TokenAmount::new(12_000_000, 6))
+        );
+
+        assert!(TokenAmount::parse_decimal("0.0000000001", Some(9)).is_none());
+        assert!(TokenAmount::parse_decimal("1e9", None).is_none());
+        assert!(TokenAmount::parse_decimal("", None).is_none());
+    }
+
+    #[test]
+    fn test_load_swap_event_fixture() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/swap_events.json");
+        let events = load_swap_events(&path).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].side, TradeSide::Buy);
+        assert_eq!(events[0].venue, Venue::PumpFun);
+        assert_eq!(events[1].side, TradeSide::Sell);
+        assert_eq!(events[1].token_amount.ui_amount(), 1_000_000.0);
+
+        // 序列化之后再读回来保持一致
+        let json = serde_json::to_string(&events).unwrap();
+        let decoded: Vec<SwapEvent> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, events);
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use crate::constant::SECS_PER_DAY;
+use crate::states::metrics::RiskMetrics;
+use crate::states::states::User;
+
+/*
+ *  滚动时间窗口
+ *  同一个钱包在最近 24h/7d/30d 内的买卖分别重新统计一遍(只统计窗口内的买卖，窗口之前的买入不参与成本计算)，
+ *  对比不同窗口的收益率与胜率，可以看出钱包的优势是否在减弱
+ */
+
+/// 默认的滚动窗口(秒): 24h/7d/30d
+pub const DEFAULT_WINDOWS: [u64; 3] = [SECS_PER_DAY, 7 * SECS_PER_DAY, 30 * SECS_PER_DAY];
+
+/// 一个时间窗口内的统计快照
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct WindowSnapshot {
+    pub window_secs: u64,  // 窗口长度
+    pub start: u64,        // 窗口起点(包含)
+    pub swap_count: usize, // 窗口内的买卖次数
+    pub token_count: usize,
+    pub total_cost: f64,   // 窗口内已卖出部分的买入成本(sol)
+    pub total_profit: f64, // 窗口内的已实现盈亏(sol)
+    pub roi: f64,          // total_profit / total_cost，没有成本时为 0
+    pub metrics: RiskMetrics,
+}
+
+impl WindowSnapshot {
+    /// 只用 `start` 之后的买卖重新统计
+    pub fn from_user(user: &User, window_secs: u64, now: u64) -> Self {
+        let start = now.saturating_sub(window_secs);
+        let mut window = User::new(user.address, 0);
+        window.cost_basis_method = user.cost_basis_method;
+        window.record_swap_events(
+            user.swap_events
+                .iter()
+                .filter(|event| event.block_time >= start)
+                .cloned()
+                .collect(),
+        );
+        let metrics = *window.compute_metrics();
+        Self {
+            window_secs,
+            start,
+            swap_count: window.swap_events.len(),
+            token_count: window.token_stats.len(),
+            total_cost: window.total_cost,
+            total_profit: window.total_profit,
+            roi: match window.total_cost > 0.0 {
+                true => window.total_profit / window.total_cost,
+                false => 0.0,
+            },
+            metrics,
+        }
+    }
+}
+
+impl Display for WindowSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let window = match self.window_secs % SECS_PER_DAY {
+            0 => format!("{}d", self.window_secs / SECS_PER_DAY),
+            _ => format!("{}h", self.window_secs / 3600),
+        };
+        write!(
+            f,
+            "[{}] swaps: {}, tokens: {}, cost: {:.4}, profit: {:.4}, roi: {:.2}%, win rate: {:.2}%",
+            window,
+            self.swap_count,
+            self.token_count,
+            self.total_cost,
+            self.total_profit,
+            self.roi * 100.0,
+            self.metrics.trade_win_rate * 100.0,
+        )
+    }
+}
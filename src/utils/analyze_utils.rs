@@ -1,114 +1,757 @@
-use std::{collections::HashMap, fs, str::FromStr};
+use std::{collections::HashMap, str::FromStr};
 
 use anyhow::Result;
 use solana_sdk::{
-    message::v0::MessageAddressTableLookup, pubkey::Pubkey, signer::Signer,
+    message::v0::MessageAddressTableLookup, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey,
     transaction::VersionedTransaction,
 };
 use solana_transaction_status_client_types::{
-    option_serializer::OptionSerializer, EncodedTransactionWithStatusMeta, UiTransactionStatusMeta,
-    UiTransactionTokenBalance,
+    UiLoadedAddresses, UiTransactionStatusMeta, UiTransactionTokenBalance,
 };
 
-use crate::constant::{PUMP_PROGRAM_ID, RAYDIUM_PROGRAM_ID};
+use crate::constant::{
+    JITO_TIP_ACCOUNTS, LAMPORTS_PER_SIGNATURE, PUMP_PROGRAM_ID, RAYDIUM_PROGRAM_ID,
+    SYSTEM_PROGRAM_PUBKEY, WSOL_MINT_PUBKEY, WSOL_MINT_STR,
+};
+use crate::states::states::User;
+use crate::states::swap_event::SwapEvent;
+use crate::utils::instruction_walker::WalkedInstruction;
+use crate::utils::swap_decoder::{DecodedTransaction, SOL_DECIMALS};
+
+/// 聪明钱包的筛选门槛
+#[derive(Debug, Clone)]
+pub struct QualificationConfig {
+    pub min_trades: usize,          // 最少买卖次数
+    pub min_distinct_tokens: usize, // 最少交易过的代币种类
+    pub min_net_pnl_sol: f64,       // 扣除成本之后最少的盈利(sol)
+    pub min_win_rate: f64,          // 最低胜率(按代币统计，只统计有卖出的代币)
+}
+
+impl Default for QualificationConfig {
+    fn default() -> Self {
+        Self {
+            min_trades: 5,
+            min_distinct_tokens: 2,
+            min_net_pnl_sol: 0.0,
+            min_win_rate: 0.0,
+        }
+    }
+}
+
+/// 排名后的钱包
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankedWallet {
+    pub address: Pubkey,
+    pub gross_pnl: i64,           // 买卖代币本身的已实现盈亏(lamports)
+    pub costs: TradeCosts,        // 手续费/优先费/小费/租金
+    pub net_pnl: i64,             // 扣除成本之后的盈亏(lamports)
+    pub trade_count: usize,       // 买卖次数
+    pub token_count: usize,       // 交易过的代币种类
+    pub win_rate: f64,            // 按代币统计的胜率
+    pub score: f64,               // 排名依据，目前为扣除成本之后的盈利(sol)
+    pub latest_slot: Option<u64>, // 该钱包买卖中最新的 slot，数据源不提供 slot 时为 None
+}
 
 // 分析交易，找到聪明钱包
 pub struct WalletAnalyzer {
-    // 盈利计算
-    profit_map: HashMap<String, u64>,
-    // token地址
-    token_address: Vec<String>,
-    // 聪明钱包地址
-    smart_wallets: Vec<String>,
+    // 盈利计算(钱包 => 代币 => 该代币的已实现盈亏，单位 lamports)
+    pub profit_map: HashMap<Pubkey, HashMap<Pubkey, i64>>,
+    // 钱包 => 手续费/优先费/小费/租金
+    pub cost_map: HashMap<Pubkey, TradeCosts>,
+    // 钱包 => 该钱包的买卖统计
+    pub users: HashMap<Pubkey, User>,
+    // token地址，为空时统计所有代币
+    pub token_address: Vec<String>,
+    // 筛选门槛
+    pub config: QualificationConfig,
+    // 聪明钱包(按 score 从高到低排序)
+    pub smart_wallets: Vec<RankedWallet>,
 }
 
 impl WalletAnalyzer {
-    pub fn new(token_address: Vec<String>) -> Self {
+    pub fn new(token_address: Vec<String>, config: QualificationConfig) -> Self {
         Self {
             token_address,
+            config,
             profit_map: HashMap::new(),
+            cost_map: HashMap::new(),
+            users: HashMap::new(),
             smart_wallets: Vec::new(),
         }
     }
 
-    // 累积盈利
-    pub fn cum_profit(&mut self, meta: &UiTransactionStatusMeta) -> Result<()> {
-        let balance_change = get_balance_change(&self.token_address, meta)?;
-        println!("balance_change {:?}", balance_change);
-        Ok(())
+    /// 一次处理多个钱包的交易，每笔交易归属到它的签名钱包
+    /// - 按时间顺序统计，卖出才能匹配到之前的买入
+    pub fn ingest(&mut self, txs: &[DecodedTransaction]) {
+        let mut txs: Vec<&DecodedTransaction> = txs.iter().collect();
+        txs.sort_by_key(|tx| (tx.block_time, tx.slot));
+        for tx in txs {
+            self.cum_profit(tx);
+        }
+    }
+
+    // 累积盈利，盈利只按 swap 本身的 sol 收支计算，手续费等成本单独统计
+    pub fn cum_profit(&mut self, tx: &DecodedTransaction) {
+        let Some(wallet) = tx.account_keys.first().copied() else {
+            return;
+        };
+        let events = tx.swap_events(&wallet);
+        if events.is_empty() {
+            return;
+        }
+        for event in events.iter() {
+            self.record_event(event);
+        }
+        if let Some((sol_change, _)) = tx.balance_change(&wallet, &[]) {
+            self.record_costs(&wallet, &sol_change.costs);
+        }
+    }
+
+    /// 记录一次买卖，盈亏按持仓批次结算，只有匹配到买入的卖出才计入
+    /// - 不是以 sol 计价的买卖直接忽略，还没卖出的买入不计为亏损
+    /// - 金额是估算出来的买卖(`estimated`)只计入交易次数，不计入盈亏
+    pub fn record_event(&mut self, event: &SwapEvent) {
+        if !self.token_address.is_empty() && !self.token_address.contains(&event.mint.to_string()) {
+            return;
+        }
+        if event.quote_mint != WSOL_MINT_PUBKEY || event.quote_amount.decimals != SOL_DECIMALS {
+            return;
+        }
+        let realized = self
+            .users
+            .entry(event.wallet)
+            .or_insert_with(|| User::new(event.wallet, 0))
+            .record_swap_event(event);
+        let pnl = self
+            .profit_map
+            .entry(event.wallet)
+            .or_default()
+            .entry(event.mint)
+            .or_insert(0);
+        if let Some(realized) = realized {
+            *pnl += realized.pnl as i64;
+        }
+    }
+
+    pub fn record_costs(&mut self, wallet: &Pubkey, costs: &TradeCosts) {
+        self.cost_map.entry(*wallet).or_default().add(costs);
+    }
+
+    /// 按门槛筛选钱包并按 score 从高到低排序
+    pub fn rank(&mut self) -> &[RankedWallet] {
+        let mut ranked: Vec<RankedWallet> = self
+            .profit_map
+            .iter()
+            .map(|(wallet, token_pnl)| {
+                let costs = self.cost_map.get(wallet).copied().unwrap_or_default();
+                let gross_pnl: i64 = token_pnl.values().sum();
+                let net_pnl = gross_pnl - costs.total();
+                let user = self.users.get(wallet);
+                let trade_count = user.map(|user| user.swap_events.len()).unwrap_or(0);
+                let latest_slot = user
+                    .and_then(|user| user.swap_events.iter().filter_map(|event| event.slot).max());
+
+                // 只统计有卖出的代币
+                let closed: Vec<&i64> = token_pnl
+                    .iter()
+                    .filter(|(mint, _)| {
+                        self.users
+                            .get(wallet)
+                            .and_then(|user| user.token_stats.get(mint))
+                            .is_some_and(|stats| !stats.closed_trades.is_empty())
+                    })
+                    .map(|(_, pnl)| pnl)
+                    .collect();
+                let win_rate = if closed.is_empty() {
+                    0.0
+                } else {
+                    closed.iter().filter(|pnl| ***pnl > 0).count() as f64 / closed.len() as f64
+                };
+
+                RankedWallet {
+                    address: *wallet,
+                    gross_pnl,
+                    costs,
+                    net_pnl,
+                    trade_count,
+                    token_count: token_pnl.len(),
+                    win_rate,
+                    score: net_pnl as f64 / LAMPORTS_PER_SOL as f64,
+                    latest_slot,
+                }
+            })
+            .filter(|wallet| {
+                wallet.trade_count >= self.config.min_trades
+                    && wallet.token_count >= self.config.min_distinct_tokens
+                    && wallet.score >= self.config.min_net_pnl_sol
+                    && wallet.win_rate >= self.config.min_win_rate
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+        self.smart_wallets = ranked;
+        &self.smart_wallets
+    }
+
+    /// 排名后的钱包对应的 User(score 已经填好)，可以直接用来构造 fundManage::SmartWallet
+    pub fn smart_wallet_users(&self) -> Vec<User> {
+        self.smart_wallets
+            .iter()
+            .filter_map(|ranked| {
+                let mut user = self.users.get(&ranked.address)?.clone();
+                user.score = ranked.score;
+                Some(user)
+            })
+            .collect()
     }
 }
 
-// 判断是否为target交易
-pub fn is_target(account_keys: &[Pubkey]) -> bool {
-    account_keys.contains(&PUMP_PROGRAM_ID) || account_keys.contains(&RAYDIUM_PROGRAM_ID)
+// 判断是否为target交易: 顶层指令或者内部指令(CPI)中调用了pump.fun或raydium
+pub fn is_target(instructions: &[WalkedInstruction]) -> bool {
+    instructions
+        .iter()
+        .any(|ix| ix.program_id == PUMP_PROGRAM_ID || ix.program_id == RAYDIUM_PROGRAM_ID)
 }
 
 pub fn string_to_pub_key(origin: &str) -> Pubkey {
     Pubkey::from_str_const(origin)
 }
 
+/// 获取交易完整的账户列表(静态账户 + 通过地址查找表加载的账户)
+/// - v0 交易中指令的账户下标可能指向查找表加载的账户，只用静态账户会漏掉 pump/raydium 程序
+/// - 交易使用了查找表但 meta 中没有 loaded_addresses 时返回 None，需要调用方去链上读取查找表
+pub fn get_full_account_keys(
+    tx: &VersionedTransaction,
+    meta: Option<&UiTransactionStatusMeta>,
+) -> Option<Vec<Pubkey>> {
+    let mut account_keys = tx.message.static_account_keys().to_vec();
+    let lookups = tx.message.address_table_lookups().unwrap_or_default();
+    if lookups.is_empty() {
+        return Some(account_keys);
+    }
+
+    let loaded: Option<&UiLoadedAddresses> = meta?.loaded_addresses.as_ref().into();
+    let loaded = loaded?;
+    // 账户顺序：静态账户 -> 所有可写的加载账户 -> 所有只读的加载账户
+    for address in loaded.writable.iter().chain(loaded.readonly.iter()) {
+        account_keys.push(Pubkey::from_str(address).ok()?);
+    }
+    Some(account_keys)
+}
+
+/// 根据查找表的内容解析 v0 交易加载的账户
+/// - `tables` 查找表地址 => 查找表中的地址列表
+/// - 返回的顺序与运行时一致：先是所有查找表的可写账户，再是所有查找表的只读账户
+pub fn resolve_lookup_addresses(
+    lookups: &[MessageAddressTableLookup],
+    tables: &HashMap<Pubkey, Vec<Pubkey>>,
+) -> Option<Vec<Pubkey>> {
+    let mut writable = vec![];
+    let mut readonly = vec![];
+    for lookup in lookups {
+        let table = tables.get(&lookup.account_key)?;
+        for index in lookup.writable_indexes.iter() {
+            writable.push(*table.get(*index as usize)?);
+        }
+        for index in lookup.readonly_indexes.iter() {
+            readonly.push(*table.get(*index as usize)?);
+        }
+    }
+    writable.extend(readonly);
+    Some(writable)
+}
+
+/// 某个代币在一笔交易中的余额变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TokenAmountChange {
+    pub amount: i128, // 余额变化(最小单位)
+    pub decimals: u8, // 代币精度
+}
+
+impl TokenAmountChange {
+    /// 按精度换算成 ui 数量
+    pub fn ui_amount(&self) -> f64 {
+        self.amount as f64 / 10f64.powi(self.decimals as i32)
+    }
+}
+
+/// 某个 token account 在一笔交易中的余额变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenBalanceChange {
+    pub account_index: u8,     // 账户在交易账户列表中的下标
+    pub mint: String,          // 代币 mint
+    pub owner: Option<String>, // token account 的 owner
+    pub decimals: u8,          // 代币精度
+    pub change: i128,          // 余额变化(最小单位)，post - pre
+}
+
+impl TokenBalanceChange {
+    /// 取反，用于把"付出"的一方转成正数
+    pub fn negate(mut self) -> Self {
+        self.change = -self.change;
+        self
+    }
+}
+
+/// 根据 account_index 找到某个 token account 交易前后的余额变化
+/// - 交易前或交易后不存在的记录(比如新建/关闭的 token account)按 0 处理
+/// - 交易前后都不存在则返回 None
+pub fn token_balance_change_by_index(
+    meta: &UiTransactionStatusMeta,
+    account_index: u8,
+) -> Option<TokenBalanceChange> {
+    fn find(
+        balances: Option<&Vec<UiTransactionTokenBalance>>,
+        account_index: u8,
+    ) -> Option<&UiTransactionTokenBalance> {
+        balances?
+            .iter()
+            .find(|balance| balance.account_index == account_index)
+    }
+    let pre = find(meta.pre_token_balances.as_ref().into(), account_index);
+    let post = find(meta.post_token_balances.as_ref().into(), account_index);
+    let base = post.or(pre)?;
+
+    let amount = |balance: Option<&UiTransactionTokenBalance>| -> Option<i128> {
+        match balance {
+            Some(balance) => balance
+                .ui_token_amount
+                .amount
+                .parse::<u64>()
+                .ok()
+                .map(i128::from),
+            None => Some(0),
+        }
+    };
+    Some(TokenBalanceChange {
+        account_index,
+        mint: base.mint.clone(),
+        owner: Option::<&String>::from(base.owner.as_ref()).cloned(),
+        decimals: base.ui_token_amount.decimals,
+        change: amount(post)? - amount(pre)?,
+    })
+}
+
+/// 找出某个 owner 的所有 token account 在交易前后的余额变化
+pub fn owner_token_balance_changes(
+    meta: &UiTransactionStatusMeta,
+    owner: &str,
+) -> Vec<TokenBalanceChange> {
+    let mut account_indexes = vec![];
+    for balances in [&meta.pre_token_balances, &meta.post_token_balances] {
+        let balances: Option<&Vec<UiTransactionTokenBalance>> = balances.as_ref().into();
+        for balance in balances.into_iter().flatten() {
+            let balance_owner: Option<&String> = balance.owner.as_ref().into();
+            if balance_owner.map(String::as_str) == Some(owner)
+                && !account_indexes.contains(&balance.account_index)
+            {
+                account_indexes.push(balance.account_index);
+            }
+        }
+    }
+    account_indexes
+        .into_iter()
+        .filter_map(|index| token_balance_change_by_index(meta, index))
+        .collect()
+}
+
+/// 一笔交易中除了 swap 本身之外的 sol 成本(lamports)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TradeCosts {
+    pub network_fee: u64,  // 基础手续费(每个签名 5000 lamports)
+    pub priority_fee: u64, // compute budget 优先费
+    pub tip: u64,          // 给 jito 的小费
+    pub rent: i64,         // 租金，新建 token account 押金为正，关闭账户退回为负
+}
+
+impl TradeCosts {
+    /// 总成本
+    pub fn total(&self) -> i64 {
+        self.network_fee as i64 + self.priority_fee as i64 + self.tip as i64 + self.rent
+    }
+
+    pub fn add(&mut self, other: &TradeCosts) {
+        self.network_fee += other.network_fee;
+        self.priority_fee += other.priority_fee;
+        self.tip += other.tip;
+        self.rent += other.rent;
+    }
+}
+
+/// 一笔交易中钱包 sol 的变化拆分
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SolChange {
+    pub total: i64,         // 钱包 sol(包括 wsol) 的总变化
+    pub swap_notional: i64, // 买卖代币本身的 sol 收支，买入为负，卖出为正
+    pub costs: TradeCosts,  // 其他成本
+}
+
 // 计算一笔交易的sol的数值改变
-fn cacl_sol_amount_change(sol_pre_balance: u64, sol_post_balance: u64) -> i64 {
-    return sol_post_balance as i64 - sol_pre_balance as i64;
+// - total = lamports 变化 + wsol 变化(通过聚合器下单时经常直接从钱包的 wsol 账户结算)
+// - swap_notional = total + 手续费 + 优先费 + 小费 + 租金
+fn cacl_sol_amount_change(
+    wallet: &Pubkey,
+    account_keys: &[Pubkey],
+    instructions: &[WalkedInstruction],
+    num_signatures: u8,
+    meta: &UiTransactionStatusMeta,
+) -> SolChange {
+    let owner = wallet.to_string();
+    let lamports_change = account_keys
+        .iter()
+        .position(|key| key == wallet)
+        .and_then(|index| {
+            Some((
+                *meta.pre_balances.get(index)?,
+                *meta.post_balances.get(index)?,
+            ))
+        })
+        .map(|(pre, post)| post as i64 - pre as i64)
+        .unwrap_or(0);
+
+    let mut costs = TradeCosts::default();
+    // 只有手续费支付者(第一个账户)才承担手续费
+    if account_keys.first() == Some(wallet) {
+        costs.network_fee = LAMPORTS_PER_SIGNATURE * num_signatures as u64;
+        costs.priority_fee = meta.fee.saturating_sub(costs.network_fee);
+    }
+    costs.tip = cacl_tip_amount(wallet, account_keys, instructions);
+
+    let mut wsol_change = 0i128;
+    for change in owner_token_balance_changes(meta, &owner) {
+        let is_wsol = change.mint == WSOL_MINT_STR;
+        if is_wsol {
+            wsol_change += change.change;
+        }
+        let index = change.account_index as usize;
+        let (Some(pre), Some(post)) = (meta.pre_balances.get(index), meta.post_balances.get(index))
+        else {
+            continue;
+        };
+        // 新建的 token account，lamports 中除了 wsol 本身之外都是租金押金
+        if *pre == 0 && *post > 0 {
+            let wrapped = if is_wsol { change.change as i64 } else { 0 };
+            costs.rent += *post as i64 - wrapped;
+        }
+        // 关闭的 token account，退回租金
+        if *pre > 0 && *post == 0 {
+            let wrapped = if is_wsol { -change.change as i64 } else { 0 };
+            costs.rent -= *pre as i64 - wrapped;
+        }
+    }
+
+    let total = lamports_change + wsol_change as i64;
+    SolChange {
+        total,
+        swap_notional: total + costs.total(),
+        costs,
+    }
+}
+
+// 统计钱包转给 jito 小费账户的 lamports(包括通过 CPI 转账的)
+fn cacl_tip_amount(
+    wallet: &Pubkey,
+    account_keys: &[Pubkey],
+    instructions: &[WalkedInstruction],
+) -> u64 {
+    // system program transfer: u32 指令号(2) + u64 lamports
+    const SYSTEM_TRANSFER_TAG: [u8; 4] = [2, 0, 0, 0];
+    instructions
+        .iter()
+        .filter(|ix| ix.program_id == SYSTEM_PROGRAM_PUBKEY)
+        .filter_map(|ix| {
+            let data = &ix.instruction.data;
+            if data.len() < 12 || data[0..4] != SYSTEM_TRANSFER_TAG {
+                return None;
+            }
+            let from = account_keys.get(*ix.instruction.accounts.first()? as usize)?;
+            let to = account_keys.get(*ix.instruction.accounts.get(1)? as usize)?;
+            if from != wallet || !JITO_TIP_ACCOUNTS.contains(to) {
+                return None;
+            }
+            Some(u64::from_le_bytes(data[4..12].try_into().unwrap()))
+        })
+        .sum()
 }
 
 // 计算相关token的数值改变
+// - 按 account_index 对齐交易前后的余额，交易中新建/关闭的 token account 缺失的一侧按 0 处理
+// - 只统计 owner 为当前分析钱包的 token account
+// - `token_address` 为空时统计该钱包所有代币的变化
 fn cacl_token_amount_change(
-    token_address: &Vec<String>,
-    pre_token_balances: &Vec<UiTransactionTokenBalance>,
-    post_token_balances: &Vec<UiTransactionTokenBalance>,
-) -> HashMap<String, i64> {
+    owner: &str,
+    token_address: &[String],
+    meta: &UiTransactionStatusMeta,
+) -> HashMap<String, TokenAmountChange> {
     // token_pub_key => amount_change
-    let mut change_map = HashMap::<String, i64>::new();
+    let mut change_map = HashMap::<String, TokenAmountChange>::new();
 
-    // 计算每一种代币的余额变化
-    for i in 0..post_token_balances.len() {
-        if pre_token_balances.len() <= i {
-            println!("pre_token_balances {:?}", pre_token_balances);
-            println!("post_token_balances {:?}", post_token_balances);
-        }
-        // 当前代币的地址
-        let pubkey = &pre_token_balances[i].mint;
+    for change in owner_token_balance_changes(meta, owner) {
         // 判断是否为需要判断的代币
-        if token_address.contains(pubkey) {
-            // 账户对应的余额变化
-            let pre_balance = &pre_token_balances[i]
-                .ui_token_amount
-                .amount
-                .parse::<i64>()
-                .unwrap();
-            let post_balance = &post_token_balances[i]
-                .ui_token_amount
-                .amount
-                .parse::<i64>()
-                .unwrap();
-            let profit = post_balance - pre_balance;
-            // 已有更新，未有创建
-            *change_map.entry(pubkey.clone()).or_insert(0) += profit;
+        if !token_address.is_empty() && !token_address.contains(&change.mint) {
+            continue;
         }
+        // 已有更新，未有创建
+        change_map
+            .entry(change.mint)
+            .or_insert(TokenAmountChange {
+                amount: 0,
+                decimals: change.decimals,
+            })
+            .amount += change.change;
     }
     change_map
 }
 
-// 获取地址的相关token的余额变化
+// 获取地址的sol与相关token的余额变化
+// - `wallet` 当前分析的钱包，只统计该钱包持有的账户
+// - `account_keys` 交易完整的账户列表
+// - `instructions` 展开后的指令(包括内部指令)，用来统计小费
+// - `num_signatures` 交易的签名数量，用来计算基础手续费
 pub fn get_balance_change(
-    token_addresses: &Vec<String>,
+    wallet: &Pubkey,
+    account_keys: &[Pubkey],
+    instructions: &[WalkedInstruction],
+    num_signatures: u8,
+    token_addresses: &[String],
     meta: &UiTransactionStatusMeta,
-) -> Result<(i64, HashMap<String, i64>)> {
-    let sol_amount = cacl_sol_amount_change(meta.pre_balances[0], meta.post_balances[0]);
-    // sol余额检查
-    let token_info = if meta.pre_token_balances.is_some() {
-        let pre_token_balances = meta.pre_token_balances.as_ref().unwrap();
-        let post_token_balances = meta.post_token_balances.as_ref().unwrap();
-        if pre_token_balances.len() == post_token_balances.len() {
-            cacl_token_amount_change(token_addresses, pre_token_balances, post_token_balances)
-        } else {
-            HashMap::new()
-        }
-        // 计算代币的变化
-    } else {
-        HashMap::new()
-    };
-    Ok((sol_amount, token_info))
+) -> Result<(SolChange, HashMap<String, TokenAmountChange>)> {
+    let sol_change =
+        cacl_sol_amount_change(wallet, account_keys, instructions, num_signatures, meta);
+    // 计算代币的变化
+    let token_info = cacl_token_amount_change(&wallet.to_string(), token_addresses, meta);
+    Ok((sol_change, token_info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_lookup_addresses() {
+        let table_a = Pubkey::new_unique();
+        let table_b = Pubkey::new_unique();
+        let a: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let b: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let tables = HashMap::from([(table_a, a.clone()), (table_b, b.clone())]);
+        let lookups = vec![
+            MessageAddressTableLookup {
+                account_key: table_a,
+                writable_indexes: vec![2],
+                readonly_indexes: vec![0, 3],
+            },
+            MessageAddressTableLookup {
+                account_key: table_b,
+                writable_indexes: vec![1],
+                readonly_indexes: vec![],
+            },
+        ];
+
+        let resolved = resolve_lookup_addresses(&lookups, &tables).unwrap();
+        assert_eq!(resolved, vec![a[2], b[1], a[0], a[3]]);
+
+        // 下标越界或者缺少查找表
+        let missing = vec![MessageAddressTableLookup {
+            account_key: Pubkey::new_unique(),
+            writable_indexes: vec![0],
+            readonly_indexes: vec![],
+        }];
+        assert!(resolve_lookup_addresses(&missing, &tables).is_none());
+    }
+
+    fn token_balance(index: u8, mint: &str, owner: &str, amount: u64) -> serde_json::Value {
+        serde_json::json!({
+            "accountIndex": index,
+            "mint": mint,
+            "owner": owner,
+            "uiTokenAmount": {
+                "uiAmount": null,
+                "decimals": 6,
+                "amount": amount.to_string(),
+                "uiAmountString": ""
+            }
+        })
+    }
+
+    #[test]
+    fn test_balance_change_with_created_and_closed_accounts() {
+        let wallet = Pubkey::new_unique();
+        let pool = Pubkey::new_unique().to_string();
+        let owner = wallet.to_string();
+        let bought = "HjQpoSuGewTosqSAhwGKKhm6g2UhPuhgKdBcsnBHpump";
+        let sold = "6xmiC8Gsp6i8owu3JMDpt38vsCGznCmW5Fzjuomqpump";
+        // 账户 3 在交易中新建(买入)，账户 4 在交易中关闭(清仓卖出)，账户 5 属于其他钱包
+        let meta: UiTransactionStatusMeta = serde_json::from_value(serde_json::json!({
+            "err": null,
+            "status": {"Ok": null},
+            "fee": 5000,
+            "preBalances": [1_000_000_000u64],
+            "postBalances": [900_000_000u64],
+            "preTokenBalances": [
+                token_balance(4, sold, &owner, 7_000_000),
+                token_balance(5, bought, &pool, 9_000_000),
+            ],
+            "postTokenBalances": [
+                token_balance(3, bought, &owner, 2_500_000),
+                token_balance(5, bought, &pool, 6_500_000),
+            ],
+        }))
+        .unwrap();
+
+        let (sol, tokens) = get_balance_change(&wallet, &[wallet], &[], 1, &[], &meta).unwrap();
+        assert_eq!(sol.total, -100_000_000);
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            tokens[bought],
+            TokenAmountChange {
+                amount: 2_500_000,
+                decimals: 6
+            }
+        );
+        assert_eq!(tokens[sold].amount, -7_000_000);
+        assert_eq!(tokens[sold].ui_amount(), -7.0);
+
+        // 只统计需要的代币
+        let (_, tokens) =
+            get_balance_change(&wallet, &[wallet], &[], 1, &[sold.to_string()], &meta).unwrap();
+        assert_eq!(tokens.len(), 1);
+    }
+
+    #[test]
+    fn test_sol_change_breakdown() {
+        let wallet = Pubkey::new_unique();
+        let tip_account = JITO_TIP_ACCOUNTS[0];
+        let account_keys = vec![
+            wallet,
+            Pubkey::new_unique(),
+            tip_account,
+            SYSTEM_PROGRAM_PUBKEY,
+        ];
+        let meme = "HjQpoSuGewTosqSAhwGKKhm6g2UhPuhgKdBcsnBHpump";
+        let (swap, rent, fee, tip) = (1_000_000_000u64, 2_039_280u64, 105_000u64, 1_000_000u64);
+
+        // 买入: 新建 ata(下标 1)，优先费 100000，给 jito 小费 1000000
+        let meta: UiTransactionStatusMeta = serde_json::from_value(serde_json::json!({
+            "err": null,
+            "status": {"Ok": null},
+            "fee": fee,
+            "preBalances": [10_000_000_000u64, 0, 50, 1],
+            "postBalances": [10_000_000_000u64 - swap - rent - fee - tip, rent, 50 + tip, 1],
+            "preTokenBalances": [],
+            "postTokenBalances": [token_balance(1, meme, &wallet.to_string(), 2_500_000)],
+        }))
+        .unwrap();
+        let mut data = vec![2, 0, 0, 0];
+        data.extend_from_slice(&tip.to_le_bytes());
+        let instructions = vec![WalkedInstruction {
+            outer_index: 0,
+            inner_index: None,
+            stack_height: 1,
+            program_id: SYSTEM_PROGRAM_PUBKEY,
+            route: vec![SYSTEM_PROGRAM_PUBKEY],
+            instruction: solana_sdk::instruction::CompiledInstruction {
+                program_id_index: 3,
+                accounts: vec![0, 2],
+                data,
+            },
+        }];
+
+        let (sol, _) =
+            get_balance_change(&wallet, &account_keys, &instructions, 1, &[], &meta).unwrap();
+        assert_eq!(sol.swap_notional, -(swap as i64));
+        assert_eq!(
+            sol.costs,
+            TradeCosts {
+                network_fee: 5000,
+                priority_fee: 100_000,
+                tip,
+                rent: rent as i64,
+            }
+        );
+        assert_eq!(sol.total, sol.swap_notional - sol.costs.total());
+    }
+
+    #[test]
+    fn test_rank_wallets() {
+        use crate::fundManage::fundManage::SmartWallet;
+        use crate::fundManage::strategy::CopyTradeStrategy;
+        use crate::states::swap_event::{TokenAmount, TradeSide, Venue};
+
+        let event = |wallet: Pubkey, mint: Pubkey, side: TradeSide, sol: u64| SwapEvent {
+            wallet,
+            mint,
+            side,
+            token_amount: TokenAmount::new(1_000_000, 6),
+            quote_amount: TokenAmount::new(sol as u128 * LAMPORTS_PER_SOL as u128, 9),
+            quote_mint: crate::constant::WSOL_MINT_PUBKEY,
+            price: 0.0,
+            slot: None,
+            block_time: 0,
+            venue: Venue::PumpFun,
+            signature: String::new(),
+            route: vec![],
+            value_usd: None,
+            cost_basis_usd: None,
+            estimated: false,
+        };
+        let (good, better, bad) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let config = QualificationConfig {
+            min_trades: 4,
+            min_net_pnl_sol: 0.1,
+            ..Default::default()
+        };
+        let mut analyzer = WalletAnalyzer::new(vec![], config);
+        for (wallet, profit) in [(good, 1), (better, 3), (bad, 0)] {
+            for mint in [a, b] {
+                analyzer.record_event(&event(wallet, mint, TradeSide::Buy, 2));
+                analyzer.record_event(&event(wallet, mint, TradeSide::Sell, 2 + profit));
+            }
+        }
+        // 还没卖出的买入不计为亏损，不是以 sol 计价的买卖忽略
+        let mut open_buy = event(better, Pubkey::new_unique(), TradeSide::Buy, 5);
+        open_buy.slot = Some(300);
+        analyzer.record_event(&open_buy);
+        let mut usdc_sell = event(good, a, TradeSide::Sell, 100);
+        usdc_sell.quote_mint = Pubkey::new_unique();
+        usdc_sell.quote_amount = TokenAmount::new(100_000_000, 6);
+        analyzer.record_event(&usdc_sell);
+        // 捆绑在一笔交易里的卖出，金额是 min_sol_output，不计入盈亏
+        let c = Pubkey::new_unique();
+        analyzer.record_event(&event(good, c, TradeSide::Buy, 1));
+        let mut bundled_sell = event(good, c, TradeSide::Sell, 50);
+        bundled_sell.estimated = true;
+        analyzer.record_event(&bundled_sell);
+
+        // good 的成本把盈利吃掉一部分
+        analyzer.record_costs(
+            &good,
+            &TradeCosts {
+                priority_fee: LAMPORTS_PER_SOL / 2,
+                ..Default::default()
+            },
+        );
+
+        let ranked = analyzer.rank();
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].address, better);
+        assert_eq!(ranked[0].net_pnl, 6 * LAMPORTS_PER_SOL as i64);
+        assert_eq!(ranked[0].latest_slot, Some(300));
+        assert_eq!(ranked[1].address, good);
+        assert_eq!(ranked[1].score, 1.5);
+        assert_eq!(ranked[1].win_rate, 1.0);
+
+        let users = analyzer.smart_wallet_users();
+        let smart_wallet = SmartWallet::new(
+            users[0].clone(),
+            1.0,
+            CopyTradeStrategy::get_default_strategy(),
+        );
+        assert_eq!(smart_wallet.address, better);
+        assert_eq!(smart_wallet.score, 6.0);
+    }
 }
@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::{bs58, instruction::CompiledInstruction, pubkey::Pubkey};
+use solana_transaction_status_client_types::{
+    UiInnerInstructions, UiInstruction, UiParsedInstruction, UiTransactionStatusMeta,
+};
+use std::collections::HashMap;
+
+use crate::utils::pump_parser::{parse_pump_instruction, PumpEvent};
+use crate::utils::raydium_parser::{parse_raydium_instruction, RaydiumSwap};
+
+/*
+ *  交易指令遍历
+ *  很多聪明钱包是通过聚合器/交易bot下单的，pump.fun、raydium 的指令是被 CPI 调用的，顶层指令里看不到
+ *  这里把顶层指令与 meta.inner_instructions 中的内部指令按执行顺序展开，并记录每条指令的调用路径
+ */
+
+/// 交易中的一条指令(顶层指令或者 CPI 调用的内部指令)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkedInstruction {
+    pub outer_index: usize,         // 所属顶层指令的下标
+    pub inner_index: Option<usize>, // 在该顶层指令的内部指令列表中的下标，顶层指令为 None
+    pub stack_height: u32,          // 调用深度，顶层指令为 1
+    pub program_id: Pubkey,         // 执行当前指令的程序
+    pub route: Vec<Pubkey>,         // 从顶层程序到当前程序的调用路径(包含当前程序)
+    pub instruction: CompiledInstruction,
+}
+
+/// 从交易中识别出的一次 swap
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DetectedSwap {
+    Pump(PumpEvent),
+    Raydium(RaydiumSwap),
+}
+
+/// 一次 swap 以及它的调用路径
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutedSwap {
+    pub wallet: Pubkey,             // 交易的签名钱包(嵌套调用的 swap 也归属到它)
+    pub route: Vec<Pubkey>,         // 调用路径，比如 [jupiter, raydium]
+    pub outer_index: usize,         // 所属顶层指令的下标
+    pub inner_index: Option<usize>, // 内部指令下标，直接调用为 None
+    pub swap: DetectedSwap,
+}
+
+impl RoutedSwap {
+    /// 是否经过其他程序(聚合器/bot)转发
+    pub fn is_routed(&self) -> bool {
+        self.route.len() > 1
+    }
+}
+
+/// 按执行顺序展开交易的所有指令
+/// - `account_keys` 交易完整的账户列表
+/// - `instructions` 交易的顶层指令
+/// - `meta` 交易的状态信息，没有 meta 或者 meta 中没有 inner_instructions 时只返回顶层指令
+pub fn walk_instructions(
+    account_keys: &[Pubkey],
+    instructions: &[CompiledInstruction],
+    meta: Option<&UiTransactionStatusMeta>,
+) -> Vec<WalkedInstruction> {
+    let inner_instructions: Option<&Vec<UiInnerInstructions>> =
+        meta.and_then(|meta| meta.inner_instructions.as_ref().into());
+    let inner_map: HashMap<usize, &UiInnerInstructions> = inner_instructions
+        .map(|list| {
+            list.iter()
+                .map(|inner| (inner.index as usize, inner))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut walked = vec![];
+    for (outer_index, instruction) in instructions.iter().enumerate() {
+        let Some(program_id) = account_keys.get(instruction.program_id_index as usize) else {
+            continue;
+        };
+        walked.push(WalkedInstruction {
+            outer_index,
+            inner_index: None,
+            stack_height: 1,
+            program_id: *program_id,
+            route: vec![*program_id],
+            instruction: instruction.clone(),
+        });
+
+        let Some(inner) = inner_map.get(&outer_index) else {
+            continue;
+        };
+        // 调用栈，stack[i] 为深度 i+1 的程序
+        let mut stack = vec![*program_id];
+        for (inner_index, ui_instruction) in inner.instructions.iter().enumerate() {
+            let Some((instruction, stack_height)) = to_compiled(account_keys, ui_instruction)
+            else {
+                continue;
+            };
+            let Some(program_id) = account_keys.get(instruction.program_id_index as usize) else {
+                continue;
+            };
+            // 老交易没有 stack_height，统一按被顶层指令直接调用处理
+            let stack_height = stack_height.unwrap_or(2).max(2);
+            stack.truncate(stack_height as usize - 1);
+            stack.push(*program_id);
+
+            walked.push(WalkedInstruction {
+                outer_index,
+                inner_index: Some(inner_index),
+                stack_height,
+                program_id: *program_id,
+                route: stack.clone(),
+                instruction,
+            });
+        }
+    }
+    walked
+}
+
+/// 从展开后的指令中找出所有 pump.fun / raydium swap
+/// - 所有 swap 都归属到交易的第一个签名者(account_keys[0])
+/// - raydium 需要通过 meta 中的 token 余额确定成交数量，没有 meta 时跳过
+pub fn find_routed_swaps(
+    account_keys: &[Pubkey],
+    walked: &[WalkedInstruction],
+    meta: Option<&UiTransactionStatusMeta>,
+) -> Vec<RoutedSwap> {
+    let Some(wallet) = account_keys.first() else {
+        return vec![];
+    };
+
+    walked
+        .iter()
+        .filter_map(|walked| {
+            let swap = if let Some(event) =
+                parse_pump_instruction(account_keys, &walked.instruction)
+                    .ok()
+                    .flatten()
+            {
+                DetectedSwap::Pump(event)
+            } else {
+                let swap = parse_raydium_instruction(account_keys, &walked.instruction, meta?)
+                    .ok()
+                    .flatten()?;
+                DetectedSwap::Raydium(swap)
+            };
+            Some(RoutedSwap {
+                wallet: *wallet,
+                route: walked.route.clone(),
+                outer_index: walked.outer_index,
+                inner_index: walked.inner_index,
+                swap,
+            })
+        })
+        .collect()
+}
+
+// 把 UiInstruction 转成 CompiledInstruction，jsonParsed 格式中完全解析的指令没有原始数据，直接跳过
+fn to_compiled(
+    account_keys: &[Pubkey],
+    instruction: &UiInstruction,
+) -> Option<(CompiledInstruction, Option<u32>)> {
+    match instruction {
+        UiInstruction::Compiled(compiled) => Some((
+            CompiledInstruction {
+                program_id_index: compiled.program_id_index,
+                accounts: compiled.accounts.clone(),
+                data: bs58::decode(&compiled.data).into_vec().ok()?,
+            },
+            compiled.stack_height,
+        )),
+        UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(decoded)) => {
+            let index_of = |address: &str| -> Option<u8> {
+                let pubkey = address.parse::<Pubkey>().ok()?;
+                let index = account_keys.iter().position(|key| *key == pubkey)?;
+                u8::try_from(index).ok()
+            };
+            let accounts = decoded
+                .accounts
+                .iter()
+                .map(|account| index_of(account))
+                .collect::<Option<Vec<u8>>>()?;
+            Some((
+                CompiledInstruction {
+                    program_id_index: index_of(&decoded.program_id)?,
+                    accounts,
+                    data: bs58::decode(&decoded.data).into_vec().ok()?,
+                },
+                decoded.stack_height,
+            ))
+        }
+        UiInstruction::Parsed(UiParsedInstruction::Parsed(_)) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::PUMP_PROGRAM_ID;
+    use crate::utils::pump_parser::PUMP_BUY_DISCRIMINATOR;
+    use serde_json::json;
+
+    #[test]
+    fn test_walk_nested_pump_buy() {
+        // 0 签名者, 1..12 pump 账户, 12 聚合器, 13 pump, 14 system program
+        let aggregator = Pubkey::new_unique();
+        let system = Pubkey::default();
+        let mut account_keys: Vec<Pubkey> = (0..12).map(|_| Pubkey::new_unique()).collect();
+        account_keys.extend([aggregator, PUMP_PROGRAM_ID, system]);
+
+        let mut buy_data = PUMP_BUY_DISCRIMINATOR.to_vec();
+        buy_data.extend_from_slice(&500u64.to_le_bytes());
+        buy_data.extend_from_slice(&10u64.to_le_bytes());
+
+        let top_level = vec![CompiledInstruction {
+            program_id_index: 12,
+            accounts: vec![0],
+            data: vec![],
+        }];
+        let meta: UiTransactionStatusMeta = serde_json::from_value(json!({
+            "err": null,
+            "status": {"Ok": null},
+            "fee": 5000,
+            "preBalances": [],
+            "postBalances": [],
+            "innerInstructions": [{
+                "index": 0,
+                "instructions": [
+                    {
+                        "programIdIndex": 13,
+                        "accounts": (0..12).collect::<Vec<u8>>(),
+                        "data": bs58::encode(&buy_data).into_string(),
+                        "stackHeight": 2
+                    },
+                    {
+                        "programIdIndex": 14,
+                        "accounts": [0, 1],
+                        "data": "",
+                        "stackHeight": 3
+                    }
+                ]
+            }]
+        }))
+        .unwrap();
+
+        let walked = walk_instructions(&account_keys, &top_level, Some(&meta));
+        assert_eq!(walked.len(), 3);
+        assert_eq!(walked[2].route, vec![aggregator, PUMP_PROGRAM_ID, system]);
+
+        let swaps = find_routed_swaps(&account_keys, &walked, Some(&meta));
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].wallet, account_keys[0]);
+        assert_eq!(swaps[0].route, vec![aggregator, PUMP_PROGRAM_ID]);
+        assert_eq!(swaps[0].inner_index, Some(0));
+        assert!(swaps[0].is_routed());
+        assert!(matches!(
+            swaps[0].swap,
+            DetectedSwap::Pump(PumpEvent::Buy { amount: 500, .. })
+        ));
+    }
+}
@@ -1 +1,7 @@
 pub mod analyze_utils;
+pub mod instruction_walker;
+pub mod pump_curve;
+pub mod pump_parser;
+pub mod raydium_parser;
+pub mod scoring;
+pub mod swap_decoder;
@@ -0,0 +1,271 @@
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::TxParseError;
+use crate::utils::swap_decoder::{PUMP_TOKEN_DECIMALS, SOL_DECIMALS};
+
+/*
+ *  pump.fun 的 BondingCurve 与 Global 账户
+ *  账户数据 = 8字节 discriminator(sha256("account:<账户名>")的前8字节) + borsh 编码的字段，
+ *  在此基础上提供不依赖网络的报价计算：
+ *  1.花一定数量的 sol 能买到多少代币
+ *  2.卖出一定数量的代币能收到多少 sol
+ *  3.当前价格、市值与迁移进度
+ */
+
+pub const BONDING_CURVE_DISCRIMINATOR: [u8; 8] = [23, 183, 248, 55, 96, 216, 172, 96];
+pub const GLOBAL_DISCRIMINATOR: [u8; 8] = [167, 232, 232, 177, 200, 108, 114, 127];
+
+// 手续费的基数(万分之一)
+const BASIS_POINTS: u128 = 10_000;
+
+/// 一个代币的 bonding curve 状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BondingCurve {
+    pub virtual_token_reserves: u64, // 虚拟代币储备(最小单位)，用于计算价格
+    pub virtual_sol_reserves: u64,   // 虚拟 sol 储备(lamports)
+    pub real_token_reserves: u64,    // 还可以买到的代币数量
+    pub real_sol_reserves: u64,      // curve 中实际的 sol
+    pub token_total_supply: u64,     // 代币总供应量
+    pub complete: bool,              // 是否已经完成(迁移到 raydium)，完成之后不能在 curve 上交易
+}
+
+/// pump.fun 的全局配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Global {
+    pub initialized: bool,
+    pub authority: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub initial_virtual_token_reserves: u64, // 新代币 curve 的初始虚拟代币储备
+    pub initial_virtual_sol_reserves: u64,   // 新代币 curve 的初始虚拟 sol 储备
+    pub initial_real_token_reserves: u64,    // 新代币 curve 可以卖出的代币数量
+    pub token_total_supply: u64,
+    pub fee_basis_points: u64, // 买卖手续费(万分之一)
+}
+
+impl Global {
+    /// 解析 Global 账户数据，账户之后新增的字段忽略
+    pub fn decode(data: &[u8]) -> Result<Self, TxParseError> {
+        let mut data = check_discriminator(data, &GLOBAL_DISCRIMINATOR, "Global")?;
+        Ok(Self {
+            initialized: read_bool(&mut data)?,
+            authority: read_pubkey(&mut data)?,
+            fee_recipient: read_pubkey(&mut data)?,
+            initial_virtual_token_reserves: read_u64(&mut data)?,
+            initial_virtual_sol_reserves: read_u64(&mut data)?,
+            initial_real_token_reserves: read_u64(&mut data)?,
+            token_total_supply: read_u64(&mut data)?,
+            fee_basis_points: read_u64(&mut data)?,
+        })
+    }
+}
+
+impl BondingCurve {
+    /// 解析 BondingCurve 账户数据，账户之后新增的字段(比如 creator)忽略
+    pub fn decode(data: &[u8]) -> Result<Self, TxParseError> {
+        let mut data = check_discriminator(data, &BONDING_CURVE_DISCRIMINATOR, "BondingCurve")?;
+        Ok(Self {
+            virtual_token_reserves: read_u64(&mut data)?,
+            virtual_sol_reserves: read_u64(&mut data)?,
+            real_token_reserves: read_u64(&mut data)?,
+            real_sol_reserves: read_u64(&mut data)?,
+            token_total_supply: read_u64(&mut data)?,
+            complete: read_bool(&mut data)?,
+        })
+    }
+
+    /// 花 `sol_in` lamports(包含手续费)能买到的代币数量(最小单位)
+    /// - 最多买到 curve 中剩余的代币，curve 已经完成时为 0
+    pub fn buy_quote(&self, sol_in: u64, fee_basis_points: u64) -> u64 {
+        if self.complete || self.virtual_sol_reserves == 0 {
+            return 0;
+        }
+        let sol_after_fee =
+            sol_in as u128 * BASIS_POINTS / (BASIS_POINTS + fee_basis_points as u128);
+        let virtual_sol = self.virtual_sol_reserves as u128;
+        let virtual_token = self.virtual_token_reserves as u128;
+        // 恒定乘积，剩余的代币向上取整，保证不会多给
+        let token_after = virtual_sol * virtual_token / (virtual_sol + sol_after_fee) + 1;
+        let token_out = virtual_token.saturating_sub(token_after);
+        token_out.min(self.real_token_reserves as u128) as u64
+    }
+
+    /// 卖出 `amount` 个代币(最小单位)能收到的 lamports(扣除手续费)
+    /// - 最多收到 curve 中实际的 sol，curve 已经完成时为 0
+    pub fn sell_quote(&self, amount: u64, fee_basis_points: u64) -> u64 {
+        if self.complete || amount == 0 {
+            return 0;
+        }
+        let amount = amount as u128;
+        let sol_out = amount * self.virtual_sol_reserves as u128
+            / (self.virtual_token_reserves as u128 + amount);
+        let fee = sol_out * fee_basis_points as u128 / BASIS_POINTS;
+        (sol_out - fee).min(self.real_sol_reserves as u128) as u64
+    }
+
+    /// 当前价格，每个代币值多少 sol
+    pub fn price(&self) -> Option<f64> {
+        if self.virtual_token_reserves == 0 {
+            return None;
+        }
+        let sol = self.virtual_sol_reserves as f64 / 10f64.powi(SOL_DECIMALS as i32);
+        let token = self.virtual_token_reserves as f64 / 10f64.powi(PUMP_TOKEN_DECIMALS as i32);
+        Some(sol / token)
+    }
+
+    /// 按当前价格计算的市值(sol)
+    pub fn market_cap(&self) -> Option<f64> {
+        let supply = self.token_total_supply as f64 / 10f64.powi(PUMP_TOKEN_DECIMALS as i32);
+        Some(self.price()? * supply)
+    }
+
+    /// 迁移进度(0 ~ 1)，即 curve 中可以卖出的代币已经卖出的比例
+    pub fn migration_progress(&self, global: &Global) -> f64 {
+        if self.complete {
+            return 1.0;
+        }
+        let initial = global.initial_real_token_reserves;
+        if initial == 0 {
+            return 0.0;
+        }
+        let sold = initial.saturating_sub(self.real_token_reserves);
+        sold as f64 / initial as f64
+    }
+}
+
+// 校验账户的 discriminator，返回之后的字段数据
+fn check_discriminator<'a>(
+    data: &'a [u8],
+    discriminator: &[u8; 8],
+    name: &str,
+) -> Result<&'a [u8], TxParseError> {
+    match data.split_at_checked(8) {
+        Some((head, rest)) if head == discriminator => Ok(rest),
+        _ => Err(TxParseError::InvalidAccount(format!(
+            "not a pump.fun {} account",
+            name
+        ))),
+    }
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], TxParseError> {
+    let (bytes, rest) = data.split_at_checked(len).ok_or_else(|| {
+        TxParseError::InvalidAccount("unexpected end of account data".to_string())
+    })?;
+    *data = rest;
+    Ok(bytes)
+}
+
+fn read_u64(data: &mut &[u8]) -> Result<u64, TxParseError> {
+    Ok(u64::from_le_bytes(take(data, 8)?.try_into().unwrap()))
+}
+
+fn read_bool(data: &mut &[u8]) -> Result<bool, TxParseError> {
+    Ok(take(data, 1)?[0] != 0)
+}
+
+fn read_pubkey(data: &mut &[u8]) -> Result<Pubkey, TxParseError> {
+    Ok(Pubkey::new_from_array(take(data, 32)?.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::hashv;
+
+    // 新代币 curve 的初始状态
+    fn initial_curve() -> BondingCurve {
+        BondingCurve {
+            virtual_token_reserves: 1_073_000_000_000_000,
+            virtual_sol_reserves: 30_000_000_000,
+            real_token_reserves: 793_100_000_000_000,
+            real_sol_reserves: 0,
+            token_total_supply: 1_000_000_000_000_000,
+            complete: false,
+        }
+    }
+
+    #[test]
+    fn test_decode_accounts() {
+        for (name, discriminator) in [
+            ("BondingCurve", BONDING_CURVE_DISCRIMINATOR),
+            ("Global", GLOBAL_DISCRIMINATOR),
+        ] {
+            let hash = hashv(&[format!("account:{}", name).as_bytes()]);
+            assert_eq!(hash.to_bytes()[..8], discriminator);
+        }
+
+        let curve = initial_curve();
+        let mut data = BONDING_CURVE_DISCRIMINATOR.to_vec();
+        for value in [
+            curve.virtual_token_reserves,
+            curve.virtual_sol_reserves,
+            curve.real_token_reserves,
+            curve.real_sol_reserves,
+            curve.token_total_supply,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.push(0);
+        // 新版本账户末尾的 creator
+        data.extend_from_slice(&[9; 32]);
+        assert_eq!(BondingCurve::decode(&data).unwrap(), curve);
+        assert!(BondingCurve::decode(&data[..40]).is_err());
+        assert!(Global::decode(&data).is_err());
+
+        let mut data = GLOBAL_DISCRIMINATOR.to_vec();
+        data.push(1);
+        data.extend_from_slice(&[1; 32]);
+        data.extend_from_slice(&[2; 32]);
+        for value in [
+            1_073_000_000_000_000u64,
+            30_000_000_000,
+            793_100_000_000_000,
+            1_000_000_000_000_000,
+            100,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        let global = Global::decode(&data).unwrap();
+        assert_eq!(global.fee_recipient, Pubkey::new_from_array([2; 32]));
+        assert_eq!(global.initial_real_token_reserves, 793_100_000_000_000);
+        assert_eq!(global.fee_basis_points, 100);
+    }
+
+    #[test]
+    fn test_quotes() {
+        let mut curve = initial_curve();
+        let global = Global {
+            initial_real_token_reserves: 793_100_000_000_000,
+            fee_basis_points: 100,
+            ..Default::default()
+        };
+
+        // 1 sol 扣除 1% 手续费之后买入
+        let token_out = curve.buy_quote(1_000_000_000, global.fee_basis_points);
+        assert_eq!(token_out, 34_281_150_129_545);
+        curve.virtual_token_reserves -= token_out;
+        curve.virtual_sol_reserves += 990_099_009;
+        curve.real_token_reserves -= token_out;
+        curve.real_sol_reserves += 990_099_009;
+
+        // 马上卖出，扣除手续费之后收到的 sol
+        assert_eq!(
+            curve.sell_quote(token_out, global.fee_basis_points),
+            980_198_018
+        );
+        assert!(
+            (curve.migration_progress(&global) - token_out as f64 / 793_100_000_000_000.0).abs()
+                < 1e-12
+        );
+
+        let price = initial_curve().price().unwrap();
+        assert!((price - 0.000000027958993).abs() < 1e-15);
+        assert!((initial_curve().market_cap().unwrap() - 27.958993476).abs() < 1e-6);
+
+        // 已经完成的 curve 不能交易
+        curve.complete = true;
+        assert_eq!(curve.buy_quote(1_000_000_000, 100), 0);
+        assert_eq!(curve.sell_quote(token_out, 100), 0);
+        assert_eq!(curve.migration_progress(&global), 1.0);
+    }
+}
@@ -0,0 +1,364 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    instruction::CompiledInstruction, pubkey::Pubkey, transaction::VersionedTransaction,
+};
+
+use crate::constant::PUMP_PROGRAM_ID;
+use crate::error::TxParseError;
+
+/*
+ *  pump.fun 指令解析
+ *  pump.fun 是 Anchor 程序，指令数据 = 8字节 discriminator(sha256("global:<指令名>")的前8字节) + borsh 编码的参数
+ *  目前只关心三种指令：
+ *  1.create: name/symbol/uri
+ *  2.buy: amount(买入的token数量) + max_sol_cost(愿意付出的最多sol)
+ *  3.sell: amount(卖出的token数量) + min_sol_output(至少收到的sol)
+ */
+
+pub const PUMP_CREATE_DISCRIMINATOR: [u8; 8] = [24, 30, 200, 40, 5, 28, 7, 119];
+pub const PUMP_BUY_DISCRIMINATOR: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
+pub const PUMP_SELL_DISCRIMINATOR: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
+
+// 各指令中我们关心的账户在 accounts 列表中的位置(参考 pump.fun 的 IDL)
+const CREATE_MINT_INDEX: usize = 0;
+const CREATE_BONDING_CURVE_INDEX: usize = 2;
+const CREATE_USER_INDEX: usize = 7;
+const CREATE_ACCOUNTS_LEN: usize = 8;
+
+const TRADE_MINT_INDEX: usize = 2;
+const TRADE_BONDING_CURVE_INDEX: usize = 3;
+const TRADE_ASSOCIATED_USER_INDEX: usize = 5;
+const TRADE_USER_INDEX: usize = 6;
+const TRADE_ACCOUNTS_LEN: usize = 7;
+
+// bonding curve 账户的 PDA 种子: ["bonding-curve", mint]
+pub const BONDING_CURVE_SEED: &[u8] = b"bonding-curve";
+
+/// pump.fun 指令的原始参数(只包含 data 部分)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PumpInstruction {
+    Create {
+        name: String,
+        symbol: String,
+        uri: String,
+    },
+    Buy {
+        amount: u64,
+        max_sol_cost: u64,
+    },
+    Sell {
+        amount: u64,
+        min_sol_output: u64,
+    },
+}
+
+/// 结合账户信息之后的 pump.fun 事件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PumpEvent {
+    Buy {
+        user: Pubkey,            // 买入者
+        mint: Pubkey,            // 代币 mint
+        bonding_curve: Pubkey,   // 代币对应的 bonding curve
+        associated_user: Pubkey, // 买入者接收代币的 token account
+        amount: u64,             // 买入的 token 数量(最小单位)
+        max_sol_cost: u64,       // 最多愿意付出的 lamports
+    },
+    Sell {
+        user: Pubkey,
+        mint: Pubkey,
+        bonding_curve: Pubkey,
+        associated_user: Pubkey,
+        amount: u64,         // 卖出的 token 数量(最小单位)
+        min_sol_output: u64, // 至少收到的 lamports
+    },
+    Create {
+        user: Pubkey, // 代币创建者
+        mint: Pubkey,
+        bonding_curve: Pubkey,
+        name: String,
+        symbol: String,
+        uri: String,
+    },
+}
+
+impl PumpEvent {
+    pub fn user(&self) -> &Pubkey {
+        match self {
+            PumpEvent::Buy { user, .. }
+            | PumpEvent::Sell { user, .. }
+            | PumpEvent::Create { user, .. } => user,
+        }
+    }
+
+    pub fn mint(&self) -> &Pubkey {
+        match self {
+            PumpEvent::Buy { mint, .. }
+            | PumpEvent::Sell { mint, .. }
+            | PumpEvent::Create { mint, .. } => mint,
+        }
+    }
+}
+
+/// 代币对应的 bonding curve 地址
+pub fn bonding_curve_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[BONDING_CURVE_SEED, mint.as_ref()], &PUMP_PROGRAM_ID).0
+}
+
+/// 解析 pump.fun 指令的 data
+/// - 不认识的 discriminator(比如 setParams、withdraw) 返回 Ok(None)
+/// - data 长度不够等情况返回错误
+pub fn decode_pump_instruction(data: &[u8]) -> Result<Option<PumpInstruction>, TxParseError> {
+    if data.len() < 8 {
+        return Err(TxParseError::InvalidInstruction(
+            "pump instruction data shorter than discriminator".to_string(),
+        ));
+    }
+    let (discriminator, mut args) = data.split_at(8);
+
+    let instruction = if discriminator == PUMP_BUY_DISCRIMINATOR {
+        PumpInstruction::Buy {
+            amount: read_u64(&mut args)?,
+            max_sol_cost: read_u64(&mut args)?,
+        }
+    } else if discriminator == PUMP_SELL_DISCRIMINATOR {
+        PumpInstruction::Sell {
+            amount: read_u64(&mut args)?,
+            min_sol_output: read_u64(&mut args)?,
+        }
+    } else if discriminator == PUMP_CREATE_DISCRIMINATOR {
+        PumpInstruction::Create {
+            name: read_string(&mut args)?,
+            symbol: read_string(&mut args)?,
+            uri: read_string(&mut args)?,
+        }
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(instruction))
+}
+
+/// 解析一条 pump.fun 的 CompiledInstruction
+/// - `account_keys` 交易的账户列表，instruction 中的下标都是指向这个列表的
+pub fn parse_pump_instruction(
+    account_keys: &[Pubkey],
+    instruction: &CompiledInstruction,
+) -> Result<Option<PumpEvent>, TxParseError> {
+    // 不是 pump.fun 的指令直接跳过
+    if account_keys.get(instruction.program_id_index as usize) != Some(&PUMP_PROGRAM_ID) {
+        return Ok(None);
+    }
+
+    let account = |position: usize| -> Result<Pubkey, TxParseError> {
+        instruction
+            .accounts
+            .get(position)
+            .and_then(|index| account_keys.get(*index as usize))
+            .copied()
+            .ok_or_else(|| {
+                TxParseError::InvalidInstruction(format!(
+                    "pump instruction account {} out of range",
+                    position
+                ))
+            })
+    };
+
+    let event = match decode_pump_instruction(&instruction.data)? {
+        Some(PumpInstruction::Buy {
+            amount,
+            max_sol_cost,
+        }) => {
+            check_accounts_len(instruction, TRADE_ACCOUNTS_LEN)?;
+            PumpEvent::Buy {
+                user: account(TRADE_USER_INDEX)?,
+                mint: account(TRADE_MINT_INDEX)?,
+                bonding_curve: account(TRADE_BONDING_CURVE_INDEX)?,
+                associated_user: account(TRADE_ASSOCIATED_USER_INDEX)?,
+                amount,
+                max_sol_cost,
+            }
+        }
+        Some(PumpInstruction::Sell {
+            amount,
+            min_sol_output,
+        }) => {
+            check_accounts_len(instruction, TRADE_ACCOUNTS_LEN)?;
+            PumpEvent::Sell {
+                user: account(TRADE_USER_INDEX)?,
+                mint: account(TRADE_MINT_INDEX)?,
+                bonding_curve: account(TRADE_BONDING_CURVE_INDEX)?,
+                associated_user: account(TRADE_ASSOCIATED_USER_INDEX)?,
+                amount,
+                min_sol_output,
+            }
+        }
+        Some(PumpInstruction::Create { name, symbol, uri }) => {
+            check_accounts_len(instruction, CREATE_ACCOUNTS_LEN)?;
+            PumpEvent::Create {
+                user: account(CREATE_USER_INDEX)?,
+                mint: account(CREATE_MINT_INDEX)?,
+                bonding_curve: account(CREATE_BONDING_CURVE_INDEX)?,
+                name,
+                symbol,
+                uri,
+            }
+        }
+        None => return Ok(None),
+    };
+    Ok(Some(event))
+}
+
+/// 解析一组指令中所有的 pump.fun 事件，无法解析的指令直接忽略
+pub fn parse_pump_events(
+    account_keys: &[Pubkey],
+    instructions: &[CompiledInstruction],
+) -> Vec<PumpEvent> {
+    instructions
+        .iter()
+        .filter_map(|ix| parse_pump_instruction(account_keys, ix).ok().flatten())
+        .collect()
+}
+
+/// 解析交易顶层指令中的 pump.fun 事件
+/// - `account_keys` 交易完整的账户列表(包括地址查找表加载的账户)
+pub fn parse_pump_transaction(
+    tx: &VersionedTransaction,
+    account_keys: &[Pubkey],
+) -> Vec<PumpEvent> {
+    parse_pump_events(account_keys, tx.message.instructions())
+}
+
+fn check_accounts_len(
+    instruction: &CompiledInstruction,
+    expected: usize,
+) -> Result<(), TxParseError> {
+    if instruction.accounts.len() < expected {
+        return Err(TxParseError::InvalidInstruction(format!(
+            "pump instruction expects at least {} accounts, got {}",
+            expected,
+            instruction.accounts.len()
+        )));
+    }
+    Ok(())
+}
+
+fn read_u64(data: &mut &[u8]) -> Result<u64, TxParseError> {
+    if data.len() < 8 {
+        return Err(TxParseError::InvalidInstruction(
+            "unexpected end of instruction data".to_string(),
+        ));
+    }
+    let (bytes, rest) = data.split_at(8);
+    *data = rest;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+// borsh 字符串: u32 长度 + utf8 字节
+fn read_string(data: &mut &[u8]) -> Result<String, TxParseError> {
+    if data.len() < 4 {
+        return Err(TxParseError::InvalidInstruction(
+            "unexpected end of instruction data".to_string(),
+        ));
+    }
+    let (len_bytes, rest) = data.split_at(4);
+    let len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+    if rest.len() < len {
+        return Err(TxParseError::InvalidInstruction(
+            "string length exceeds instruction data".to_string(),
+        ));
+    }
+    let (bytes, rest) = rest.split_at(len);
+    *data = rest;
+    String::from_utf8(bytes.to_vec())
+        .map_err(|_| TxParseError::InvalidInstruction("string is not valid utf8".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade_data(discriminator: [u8; 8], amount: u64, sol: u64) -> Vec<u8> {
+        let mut data = discriminator.to_vec();
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&sol.to_le_bytes());
+        data
+    }
+
+    fn keys(n: u8) -> Vec<Pubkey> {
+        (0..n).map(|i| Pubkey::new_from_array([i; 32])).collect()
+    }
+
+    #[test]
+    fn test_decode_buy_and_sell() {
+        let buy = decode_pump_instruction(&trade_data(PUMP_BUY_DISCRIMINATOR, 1_000, 50))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            buy,
+            PumpInstruction::Buy {
+                amount: 1_000,
+                max_sol_cost: 50
+            }
+        );
+
+        let sell = decode_pump_instruction(&trade_data(PUMP_SELL_DISCRIMINATOR, 7, 3))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            sell,
+            PumpInstruction::Sell {
+                amount: 7,
+                min_sol_output: 3
+            }
+        );
+
+        // 未知指令与截断的数据
+        assert!(decode_pump_instruction(&[0; 24]).unwrap().is_none());
+        assert!(decode_pump_instruction(&PUMP_BUY_DISCRIMINATOR).is_err());
+    }
+
+    #[test]
+    fn test_decode_create() {
+        let mut data = PUMP_CREATE_DISCRIMINATOR.to_vec();
+        for s in ["Doll", "DOLL", "https://example.com/doll.json"] {
+            data.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            data.extend_from_slice(s.as_bytes());
+        }
+        let create = decode_pump_instruction(&data).unwrap().unwrap();
+        assert_eq!(
+            create,
+            PumpInstruction::Create {
+                name: "Doll".to_string(),
+                symbol: "DOLL".to_string(),
+                uri: "https://example.com/doll.json".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_pump_instruction_accounts() {
+        // 账户列表: 0..12 为指令账户, 12 为 pump 程序
+        let mut account_keys = keys(12);
+        account_keys.push(PUMP_PROGRAM_ID);
+        let instruction = CompiledInstruction {
+            program_id_index: 12,
+            accounts: (0..12).collect(),
+            data: trade_data(PUMP_SELL_DISCRIMINATOR, 100, 1),
+        };
+
+        let event = parse_pump_instruction(&account_keys, &instruction)
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.user(), &account_keys[6]);
+        assert_eq!(event.mint(), &account_keys[2]);
+        assert!(matches!(event, PumpEvent::Sell { amount: 100, .. }));
+
+        // 非 pump 程序的指令不解析
+        let other = CompiledInstruction {
+            program_id_index: 0,
+            ..instruction
+        };
+        assert!(parse_pump_instruction(&account_keys, &other)
+            .unwrap()
+            .is_none());
+    }
+}
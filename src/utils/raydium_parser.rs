@@ -0,0 +1,302 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::{instruction::CompiledInstruction, pubkey::Pubkey};
+use solana_transaction_status_client_types::UiTransactionStatusMeta;
+use std::str::FromStr;
+
+use crate::constant::RAYDIUM_PROGRAM_ID;
+use crate::error::TxParseError;
+use crate::utils::analyze_utils::token_balance_change_by_index;
+
+/*
+ *  raydium AMM v4 指令解析
+ *  AMM v4 不是 Anchor 程序，指令数据 = 1字节指令号 + 参数
+ *  1.swapBaseIn(9): amount_in + minimum_amount_out
+ *  2.swapBaseOut(11): max_amount_in + amount_out
+ *  指令里的数量只是用户给出的限制，真正成交的数量需要通过池子 vault 在交易前后的余额变化得到
+ */
+
+pub const RAYDIUM_SWAP_BASE_IN_TAG: u8 = 9;
+pub const RAYDIUM_SWAP_BASE_OUT_TAG: u8 = 11;
+
+// swap 指令有 18 个账户(带 amm_target_orders) 和 17 个账户两种形式
+// amm 固定在第二个位置，其他我们关心的账户都按从后往前的位置来取
+const AMM_INDEX: usize = 1;
+const COIN_VAULT_FROM_END: usize = 13;
+const PC_VAULT_FROM_END: usize = 12;
+const USER_SOURCE_FROM_END: usize = 3;
+const USER_DESTINATION_FROM_END: usize = 2;
+const USER_OWNER_FROM_END: usize = 1;
+const SWAP_MIN_ACCOUNTS_LEN: usize = 17;
+
+/// raydium swap 指令的原始参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RaydiumInstruction {
+    SwapBaseIn {
+        amount_in: u64,
+        minimum_amount_out: u64,
+    },
+    SwapBaseOut {
+        max_amount_in: u64,
+        amount_out: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RaydiumSwapKind {
+    BaseIn,
+    BaseOut,
+}
+
+/// 一次 raydium swap 的完整信息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RaydiumSwap {
+    pub kind: RaydiumSwapKind,
+    pub amm: Pubkey,              // 池子地址
+    pub user: Pubkey,             // 发起 swap 的钱包(user source owner)
+    pub user_source: Pubkey,      // 用户付出代币的 token account
+    pub user_destination: Pubkey, // 用户收到代币的 token account
+    pub coin_vault: Pubkey,       // 池子的 coin vault
+    pub pc_vault: Pubkey,         // 池子的 pc vault
+
+    pub input_mint: Pubkey,  // 付出的代币
+    pub output_mint: Pubkey, // 收到的代币
+    pub amount_in: u64,      // 实际付出的数量(最小单位)
+    pub amount_out: u64,     // 实际收到的数量(最小单位)
+    pub input_decimals: u8,
+    pub output_decimals: u8,
+}
+
+/// 解析 raydium 指令的 data，非 swap 指令返回 Ok(None)
+pub fn decode_raydium_instruction(data: &[u8]) -> Result<Option<RaydiumInstruction>, TxParseError> {
+    let Some((tag, args)) = data.split_first() else {
+        return Err(TxParseError::InvalidInstruction(
+            "empty raydium instruction data".to_string(),
+        ));
+    };
+    if *tag != RAYDIUM_SWAP_BASE_IN_TAG && *tag != RAYDIUM_SWAP_BASE_OUT_TAG {
+        return Ok(None);
+    }
+    if args.len() < 16 {
+        return Err(TxParseError::InvalidInstruction(
+            "raydium swap instruction data too short".to_string(),
+        ));
+    }
+    let first = u64::from_le_bytes(args[0..8].try_into().unwrap());
+    let second = u64::from_le_bytes(args[8..16].try_into().unwrap());
+
+    let instruction = if *tag == RAYDIUM_SWAP_BASE_IN_TAG {
+        RaydiumInstruction::SwapBaseIn {
+            amount_in: first,
+            minimum_amount_out: second,
+        }
+    } else {
+        RaydiumInstruction::SwapBaseOut {
+            max_amount_in: first,
+            amount_out: second,
+        }
+    };
+    Ok(Some(instruction))
+}
+
+/// 解析一条 raydium 的 CompiledInstruction
+/// - `account_keys` 交易的账户列表
+/// - `meta` 交易的状态信息，用来通过 token 余额变化还原真实的成交数量
+pub fn parse_raydium_instruction(
+    account_keys: &[Pubkey],
+    instruction: &CompiledInstruction,
+    meta: &UiTransactionStatusMeta,
+) -> Result<Option<RaydiumSwap>, TxParseError> {
+    if account_keys.get(instruction.program_id_index as usize) != Some(&RAYDIUM_PROGRAM_ID) {
+        return Ok(None);
+    }
+    let kind = match decode_raydium_instruction(&instruction.data)? {
+        Some(RaydiumInstruction::SwapBaseIn { .. }) => RaydiumSwapKind::BaseIn,
+        Some(RaydiumInstruction::SwapBaseOut { .. }) => RaydiumSwapKind::BaseOut,
+        None => return Ok(None),
+    };
+
+    let accounts_len = instruction.accounts.len();
+    if accounts_len < SWAP_MIN_ACCOUNTS_LEN {
+        return Err(TxParseError::InvalidInstruction(format!(
+            "raydium swap expects at least {} accounts, got {}",
+            SWAP_MIN_ACCOUNTS_LEN, accounts_len
+        )));
+    }
+    // 返回账户在交易账户列表中的下标
+    let account_index = |position: usize| -> Result<u8, TxParseError> {
+        let index = instruction.accounts[position];
+        if (index as usize) < account_keys.len() {
+            Ok(index)
+        } else {
+            Err(TxParseError::InvalidInstruction(format!(
+                "raydium swap account {} out of range",
+                position
+            )))
+        }
+    };
+    let coin_vault = account_index(accounts_len - COIN_VAULT_FROM_END)?;
+    let pc_vault = account_index(accounts_len - PC_VAULT_FROM_END)?;
+    let user_source = account_index(accounts_len - USER_SOURCE_FROM_END)?;
+    let user_destination = account_index(accounts_len - USER_DESTINATION_FROM_END)?;
+
+    // 优先用池子 vault 的余额变化：增加的一边是输入，减少的一边是输出
+    // vault 在交易前后一定存在，而用户的 wsol 临时账户可能在同一笔交易里被创建又关闭
+    let coin_change = token_balance_change_by_index(meta, coin_vault);
+    let pc_change = token_balance_change_by_index(meta, pc_vault);
+    let (input, output) = match (coin_change, pc_change) {
+        (Some(coin), Some(pc)) if coin.change > 0 && pc.change < 0 => (coin, pc),
+        (Some(coin), Some(pc)) if pc.change > 0 && coin.change < 0 => (pc, coin),
+        _ => {
+            // vault 信息不全时退回到用户账户的余额变化
+            match (
+                token_balance_change_by_index(meta, user_source),
+                token_balance_change_by_index(meta, user_destination),
+            ) {
+                (Some(source), Some(destination)) => (source.negate(), destination),
+                _ => {
+                    return Err(TxParseError::InvalidInstruction(
+                        "can not resolve raydium swap amounts from token balances".to_string(),
+                    ))
+                }
+            }
+        }
+    };
+
+    let key = |index: u8| account_keys[index as usize];
+    let mint = |mint: &str| {
+        Pubkey::from_str(mint)
+            .map_err(|_| TxParseError::InvalidField(format!("token balance mint: {}", mint)))
+    };
+    Ok(Some(RaydiumSwap {
+        kind,
+        amm: key(account_index(AMM_INDEX)?),
+        user: key(account_index(accounts_len - USER_OWNER_FROM_END)?),
+        user_source: key(user_source),
+        user_destination: key(user_destination),
+        coin_vault: key(coin_vault),
+        pc_vault: key(pc_vault),
+        input_mint: mint(&input.mint)?,
+        output_mint: mint(&output.mint)?,
+        amount_in: input.change.unsigned_abs() as u64,
+        amount_out: output.change.unsigned_abs() as u64,
+        input_decimals: input.decimals,
+        output_decimals: output.decimals,
+    }))
+}
+
+/// 解析一组指令中所有的 raydium swap，无法解析的指令直接忽略
+pub fn parse_raydium_swaps(
+    account_keys: &[Pubkey],
+    instructions: &[CompiledInstruction],
+    meta: &UiTransactionStatusMeta,
+) -> Vec<RaydiumSwap> {
+    instructions
+        .iter()
+        .filter_map(|ix| {
+            parse_raydium_instruction(account_keys, ix, meta)
+                .ok()
+                .flatten()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn swap_data(tag: u8, first: u64, second: u64) -> Vec<u8> {
+        let mut data = vec![tag];
+        data.extend_from_slice(&first.to_le_bytes());
+        data.extend_from_slice(&second.to_le_bytes());
+        data
+    }
+
+    fn token_balance(index: u8, mint: &str, amount: u64, decimals: u8) -> serde_json::Value {
+        json!({
+            "accountIndex": index,
+            "mint": mint,
+            "uiTokenAmount": {
+                "uiAmount": null,
+                "decimals": decimals,
+                "amount": amount.to_string(),
+                "uiAmountString": ""
+            }
+        })
+    }
+
+    #[test]
+    fn test_decode_raydium_instruction() {
+        assert_eq!(
+            decode_raydium_instruction(&swap_data(9, 100, 90)).unwrap(),
+            Some(RaydiumInstruction::SwapBaseIn {
+                amount_in: 100,
+                minimum_amount_out: 90
+            })
+        );
+        assert_eq!(
+            decode_raydium_instruction(&swap_data(11, 100, 90)).unwrap(),
+            Some(RaydiumInstruction::SwapBaseOut {
+                max_amount_in: 100,
+                amount_out: 90
+            })
+        );
+        assert!(decode_raydium_instruction(&[1, 2, 3]).unwrap().is_none());
+        assert!(decode_raydium_instruction(&[9, 0]).is_err());
+    }
+
+    #[test]
+    fn test_parse_raydium_swap_from_vaults() {
+        // 0..17 为 17 个 swap 账户, 17 为 raydium 程序
+        let mut account_keys: Vec<Pubkey> =
+            (0..17).map(|i| Pubkey::new_from_array([i; 32])).collect();
+        account_keys.push(RAYDIUM_PROGRAM_ID);
+        let instruction = CompiledInstruction {
+            program_id_index: 17,
+            accounts: (0..17).collect(),
+            data: swap_data(9, 1_000_000_000, 1),
+        };
+        let wsol = "So11111111111111111111111111111111111111112";
+        let meme = "HjQpoSuGewTosqSAhwGKKhm6g2UhPuhgKdBcsnBHpump";
+        // coin vault = 4, pc vault = 5
+        let meta = |meme: &str| -> UiTransactionStatusMeta {
+            serde_json::from_value(json!({
+                "err": null,
+                "status": {"Ok": null},
+                "fee": 5000,
+                "preBalances": [],
+                "postBalances": [],
+                "preTokenBalances": [
+                    token_balance(4, meme, 5_000_000, 6),
+                    token_balance(5, wsol, 80_000_000_000, 9),
+                ],
+                "postTokenBalances": [
+                    token_balance(4, meme, 4_000_000, 6),
+                    token_balance(5, wsol, 81_000_000_000, 9),
+                ],
+            }))
+            .unwrap()
+        };
+        let meta_with_bad_mint = meta("not-a-mint");
+        let meta = meta(meme);
+
+        let swap = parse_raydium_instruction(&account_keys, &instruction, &meta)
+            .unwrap()
+            .unwrap();
+        assert_eq!(swap.kind, RaydiumSwapKind::BaseIn);
+        assert_eq!(swap.amm, account_keys[1]);
+        assert_eq!(swap.user, account_keys[16]);
+        assert_eq!(swap.user_source, account_keys[14]);
+        assert_eq!(swap.input_mint, Pubkey::from_str(wsol).unwrap());
+        assert_eq!(swap.output_mint, Pubkey::from_str(meme).unwrap());
+        assert_eq!(swap.amount_in, 1_000_000_000);
+        assert_eq!(swap.amount_out, 1_000_000);
+        assert_eq!(swap.output_decimals, 6);
+
+        // rpc 返回的 mint 格式不对时返回错误，不会 panic
+        assert!(matches!(
+            parse_raydium_instruction(&account_keys, &instruction, &meta_with_bad_mint),
+            Err(TxParseError::InvalidField(_))
+        ));
+    }
+}
@@ -0,0 +1,510 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::constant::SECS_PER_DAY;
+use crate::error::Error;
+use crate::states::metrics::{gains_and_losses, max_drawdown};
+use crate::states::states::User;
+
+/*
+ *  多因子的钱包评分
+ *  每个因子先从 User 中算出原始值，再按各自的方式归一化到 [0, 1]，最后按配置的权重加权平均成 0 ~ 100 的总分：
+ *  1.胜率: 已平仓交易中盈利的比例
+ *  2.盈亏比(profit factor): 总盈利 / 总亏损
+ *  3.盈利集中度: 盈利最多的代币占全部盈利的比例，越分散越好
+ *  4.持仓时间: 按成本加权的平均持有时间，持有时间太短(狙击/机器人)很难跟单
+ *  5.周转率: 每天的买卖次数，太少说明不活跃，太多一般是机器人
+ *  6.回撤: 已实现盈亏曲线的最大回撤 / 已平仓交易的总成本
+ *  7.总收益率: (已实现 + 未实现盈亏) / (已卖出 + 剩余持仓的成本)，没卖出的亏损持仓同样会拉低评分
+ *  数据不足(比如没有已平仓的交易)的因子按 0 分计算
+ */
+
+/// 一个评分因子
+pub trait ScoreFactor: Send + Sync {
+    /// 因子名称，与配置中的 `factor` 一致
+    fn name(&self) -> &'static str;
+
+    /// 从 User 中算出原始值，数据不足时为 None
+    fn measure(&self, user: &User) -> Option<f64>;
+
+    /// 把原始值归一化到 [0, 1]，越大越好
+    fn normalize(&self, value: f64) -> f64;
+}
+
+/// 评分器
+pub trait Scorer: Send + Sync {
+    fn score(&self, user: &User) -> ScoreCard;
+}
+
+/// 单个因子的得分
+#[derive(Debug, Clone, PartialEq)]
+pub struct FactorScore {
+    pub name: &'static str,
+    pub value: Option<f64>, // 原始值
+    pub normalized: f64,    // 归一化之后的值，数据不足时为 0
+    pub weight: f64,
+}
+
+/// 评分结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScoreCard {
+    pub total: f64, // 0 ~ 100
+    pub factors: Vec<FactorScore>,
+}
+
+/// 胜率
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct WinRate {}
+
+impl ScoreFactor for WinRate {
+    fn name(&self) -> &'static str {
+        "win_rate"
+    }
+
+    fn measure(&self, user: &User) -> Option<f64> {
+        let trades = user.closed_trades();
+        if trades.is_empty() {
+            return None;
+        }
+        let wins = trades.iter().filter(|trade| trade.pnl > 0.0).count();
+        Some(wins as f64 / trades.len() as f64)
+    }
+
+    fn normalize(&self, value: f64) -> f64 {
+        value.clamp(0.0, 1.0)
+    }
+}
+
+/// 盈亏比，超过 `cap` 的按满分计算
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfitFactor {
+    pub cap: f64,
+}
+
+impl Default for ProfitFactor {
+    fn default() -> Self {
+        Self { cap: 3.0 }
+    }
+}
+
+impl ScoreFactor for ProfitFactor {
+    fn name(&self) -> &'static str {
+        "profit_factor"
+    }
+
+    fn measure(&self, user: &User) -> Option<f64> {
+        let trades = user.closed_trades();
+        if trades.is_empty() {
+            return None;
+        }
+        let (gains, losses) = gains_and_losses(trades.iter().map(|trade| trade.pnl));
+        match losses > 0.0 {
+            true => Some(gains / losses),
+            // 没有亏损时按上限计算
+            false if gains > 0.0 => Some(self.cap),
+            false => Some(0.0),
+        }
+    }
+
+    fn normalize(&self, value: f64) -> f64 {
+        if self.cap <= 0.0 {
+            return 0.0;
+        }
+        (value / self.cap).clamp(0.0, 1.0)
+    }
+}
+
+/// 盈利集中度，即盈利最多的代币占全部代币盈利的比例
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PnlConcentration {}
+
+impl ScoreFactor for PnlConcentration {
+    fn name(&self) -> &'static str {
+        "pnl_concentration"
+    }
+
+    fn measure(&self, user: &User) -> Option<f64> {
+        let profits: Vec<f64> = user
+            .token_stats
+            .values()
+            .map(|stats| stats.profit)
+            .filter(|profit| *profit > 0.0)
+            .collect();
+        let total: f64 = profits.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+        Some(profits.iter().cloned().fold(0.0, f64::max) / total)
+    }
+
+    // 全部盈利来自一个代币时为 0
+    fn normalize(&self, value: f64) -> f64 {
+        (1.0 - value).clamp(0.0, 1.0)
+    }
+}
+
+/// 按成本加权的平均持有时间(秒)，达到 `target_secs` 时满分
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HoldingTime {
+    pub target_secs: f64,
+}
+
+impl Default for HoldingTime {
+    fn default() -> Self {
+        Self {
+            target_secs: 3_600.0,
+        }
+    }
+}
+
+impl ScoreFactor for HoldingTime {
+    fn name(&self) -> &'static str {
+        "holding_time"
+    }
+
+    fn measure(&self, user: &User) -> Option<f64> {
+        let trades = user.closed_trades();
+        let cost: f64 = trades.iter().map(|trade| trade.cost).sum();
+        if cost <= 0.0 {
+            return None;
+        }
+        let weighted: f64 = trades
+            .iter()
+            .map(|trade| trade.holding_secs as f64 * trade.cost)
+            .sum();
+        Some(weighted / cost)
+    }
+
+    fn normalize(&self, value: f64) -> f64 {
+        if self.target_secs <= 0.0 {
+            return 1.0;
+        }
+        (value / self.target_secs).clamp(0.0, 1.0)
+    }
+}
+
+/// 每天的买卖次数，等于 `target_per_day` 时满分，偏离越多分数越低
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Turnover {
+    pub target_per_day: f64,
+}
+
+impl Default for Turnover {
+    fn default() -> Self {
+        Self {
+            target_per_day: 5.0,
+        }
+    }
+}
+
+impl ScoreFactor for Turnover {
+    fn name(&self) -> &'static str {
+        "turnover"
+    }
+
+    // 时间窗口为 0(不限制)时按第一次到最后一次买卖的时间计算，至少一天
+    fn measure(&self, user: &User) -> Option<f64> {
+        if user.swap_events.is_empty() {
+            return None;
+        }
+        let days = match user.time_day {
+            0 => {
+                let times = user.swap_events.iter().map(|event| event.block_time);
+                let first = times.clone().min().unwrap_or_default();
+                let last = times.max().unwrap_or_default();
+                ((last - first) as f64 / SECS_PER_DAY as f64).max(1.0)
+            }
+            time_day => time_day as f64,
+        };
+        Some(user.swap_events.len() as f64 / days)
+    }
+
+    fn normalize(&self, value: f64) -> f64 {
+        if value <= 0.0 || self.target_per_day <= 0.0 {
+            return 0.0;
+        }
+        match value <= self.target_per_day {
+            true => value / self.target_per_day,
+            false => self.target_per_day / value,
+        }
+    }
+}
+
+/// 已实现盈亏曲线的最大回撤占已平仓交易总成本的比例
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Drawdown {}
+
+impl ScoreFactor for Drawdown {
+    fn name(&self) -> &'static str {
+        "drawdown"
+    }
+
+    fn measure(&self, user: &User) -> Option<f64> {
+        let trades = user.closed_trades();
+        let cost: f64 = trades.iter().map(|trade| trade.cost).sum();
+        if cost <= 0.0 {
+            return None;
+        }
+        Some(max_drawdown(&trades) / cost)
+    }
+
+    // 回撤达到全部成本时为 0
+    fn normalize(&self, value: f64) -> f64 {
+        (1.0 - value).clamp(0.0, 1.0)
+    }
+}
+
+/// 包括剩余持仓的总收益率，达到 `target` 时满分
+/// - 未实现盈亏来自 `User::mark_to_market`，没有估值时只有已实现的部分
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TotalReturn {
+    pub target: f64,
+}
+
+impl Default for TotalReturn {
+    fn default() -> Self {
+        Self { target: 1.0 }
+    }
+}
+
+impl ScoreFactor for TotalReturn {
+    fn name(&self) -> &'static str {
+        "total_return"
+    }
+
+    fn measure(&self, user: &User) -> Option<f64> {
+        let cost = user.total_cost + user.open_cost;
+        if cost <= 0.0 {
+            return None;
+        }
+        Some((user.total_profit + user.unrealized_pnl) / cost)
+    }
+
+    // 亏损时为 0
+    fn normalize(&self, value: f64) -> f64 {
+        if self.target <= 0.0 {
+            return 0.0;
+        }
+        (value / self.target).clamp(0.0, 1.0)
+    }
+}
+
+/// 配置中的一个因子及其参数
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "factor", rename_all = "snake_case")]
+pub enum FactorConfig {
+    WinRate(WinRate),
+    ProfitFactor(ProfitFactor),
+    PnlConcentration(PnlConcentration),
+    HoldingTime(HoldingTime),
+    Turnover(Turnover),
+    Drawdown(Drawdown),
+    TotalReturn(TotalReturn),
+}
+
+impl FactorConfig {
+    pub fn build(&self) -> Box<dyn ScoreFactor> {
+        match *self {
+            FactorConfig::WinRate(factor) => Box::new(factor),
+            FactorConfig::ProfitFactor(factor) => Box::new(factor),
+            FactorConfig::PnlConcentration(factor) => Box::new(factor),
+            FactorConfig::HoldingTime(factor) => Box::new(factor),
+            FactorConfig::Turnover(factor) => Box::new(factor),
+            FactorConfig::Drawdown(factor) => Box::new(factor),
+            FactorConfig::TotalReturn(factor) => Box::new(factor),
+        }
+    }
+}
+
+/// 带权重的因子配置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WeightedFactor {
+    pub weight: f64,
+    #[serde(flatten)]
+    pub factor: FactorConfig,
+}
+
+/// 评分配置: 使用哪些因子以及各自的权重
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoringConfig {
+    pub factors: Vec<WeightedFactor>,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        let weighted = |weight, factor| WeightedFactor { weight, factor };
+        Self {
+            factors: vec![
+                weighted(0.2, FactorConfig::WinRate(WinRate::default())),
+                weighted(0.2, FactorConfig::ProfitFactor(ProfitFactor::default())),
+                weighted(
+                    0.1,
+                    FactorConfig::PnlConcentration(PnlConcentration::default()),
+                ),
+                weighted(0.1, FactorConfig::HoldingTime(HoldingTime::default())),
+                weighted(0.1, FactorConfig::Turnover(Turnover::default())),
+                weighted(0.15, FactorConfig::Drawdown(Drawdown::default())),
+                weighted(0.15, FactorConfig::TotalReturn(TotalReturn::default())),
+            ],
+        }
+    }
+}
+
+impl ScoringConfig {
+    /// 从 json 文件读取: {"factors": [{"factor": "win_rate", "weight": 0.3}, ...]}
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = fs::read(path)
+            .map_err(|err| Error::ConfigError(format!("{}: {:?}", path.display(), err)))?;
+        serde_json::from_slice(&data)
+            .map_err(|err| Error::ConfigError(format!("{}: {:?}", path.display(), err)))
+    }
+}
+
+/// 按权重加权平均各个因子的评分器
+pub struct WeightedScorer {
+    pub factors: Vec<(f64, Box<dyn ScoreFactor>)>,
+}
+
+impl WeightedScorer {
+    pub fn new() -> Self {
+        Self { factors: vec![] }
+    }
+
+    pub fn from_config(config: &ScoringConfig) -> Self {
+        let mut scorer = Self::new();
+        for weighted in &config.factors {
+            scorer
+                .factors
+                .push((weighted.weight, weighted.factor.build()));
+        }
+        scorer
+    }
+
+    /// 添加一个因子，权重不大于 0 的因子不参与评分
+    pub fn with_factor(mut self, weight: f64, factor: impl ScoreFactor + 'static) -> Self {
+        self.factors.push((weight, Box::new(factor)));
+        self
+    }
+}
+
+impl Default for WeightedScorer {
+    fn default() -> Self {
+        Self::from_config(&ScoringConfig::default())
+    }
+}
+
+impl Scorer for WeightedScorer {
+    fn score(&self, user: &User) -> ScoreCard {
+        let mut card = ScoreCard::default();
+        let mut total_weight = 0.0;
+        for (weight, factor) in self.factors.iter().filter(|(weight, _)| *weight > 0.0) {
+            let value = factor.measure(user);
+            let normalized = value.map(|value| factor.normalize(value)).unwrap_or(0.0);
+            card.total += weight * normalized;
+            total_weight += weight;
+            card.factors.push(FactorScore {
+                name: factor.name(),
+                value,
+                normalized,
+                weight: *weight,
+            });
+        }
+        if total_weight > 0.0 {
+            card.total = card.total / total_weight * 100.0;
+        }
+        card
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::states::swap_event::test_support::swap;
+    use crate::states::swap_event::TradeSide;
+    use solana_sdk::pubkey::Pubkey;
+    use std::collections::HashMap;
+
+    // 每个代币 1 sol 买入，`hold` 秒之后以 `proceeds` lamports 全部卖出
+    fn round_trips(trades: &[(u64, u128)]) -> User {
+        let mut user = User::new(Pubkey::new_from_array([1; 32]), 1);
+        let mut events = vec![];
+        for (i, (hold, proceeds)) in trades.iter().enumerate() {
+            let mint = Pubkey::new_unique();
+            let start = 1_000 * i as u64;
+            events.push(swap(mint, TradeSide::Buy, 1_000, 1_000_000_000, start));
+            events.push(swap(mint, TradeSide::Sell, 1_000, *proceeds, start + hold));
+        }
+        user.record_swap_events(events);
+        user
+    }
+
+    #[test]
+    fn test_factors() {
+        // 赚 1 sol，亏 0.5 sol，赚 0.5 sol
+        let user = round_trips(&[
+            (600, 2_000_000_000),
+            (60, 500_000_000),
+            (300, 1_500_000_000),
+        ]);
+        let value = |factor: &dyn ScoreFactor| factor.measure(&user).unwrap();
+
+        assert!((value(&WinRate {}) - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(value(&ProfitFactor::default()), 3.0);
+        assert_eq!(value(&PnlConcentration {}), 1.0 / 1.5);
+        assert_eq!(value(&HoldingTime::default()), 320.0);
+        assert_eq!(value(&Turnover::default()), 6.0);
+        assert_eq!(value(&Drawdown {}), 0.5 / 3.0);
+        assert_eq!(Turnover::default().normalize(10.0), 0.5);
+        assert_eq!(value(&TotalReturn::default()), 1.0 / 3.0);
+
+        // 还没卖出的持仓跌了 90%，已实现的收益被抵消
+        let mut holder = user.clone();
+        let bag = Pubkey::new_unique();
+        holder.record_swap_event(&swap(bag, TradeSide::Buy, 1_000, 2_000_000_000, 5_000));
+        holder.mark_to_market(&HashMap::from([(bag, 200.0)]));
+        assert!((TotalReturn::default().measure(&holder).unwrap() + 0.8 / 5.0).abs() < 1e-9);
+        let scorer = WeightedScorer::default();
+        assert!(scorer.score(&holder).total < scorer.score(&user).total);
+
+        // 没有任何交易时所有因子都没有数据
+        let idle = User::new(Pubkey::new_unique(), 1);
+        let card = WeightedScorer::default().score(&idle);
+        assert_eq!(card.total, 0.0);
+        assert!(card.factors.iter().all(|factor| factor.value.is_none()));
+    }
+
+    #[test]
+    fn test_scorer_from_config() {
+        let config: ScoringConfig = serde_json::from_str(
+            r#"{"factors": [
+                {"factor": "win_rate", "weight": 1.0},
+                {"factor": "profit_factor", "weight": 1.0, "cap": 2.0},
+                {"factor": "turnover", "weight": 0.0}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.factors[1].factor,
+            FactorConfig::ProfitFactor(ProfitFactor { cap: 2.0 })
+        );
+
+        // 胜率 1/2，盈亏比 1.5 / 0.5 超过上限
+        let mut user = round_trips(&[(60, 2_500_000_000), (60, 500_000_000)]);
+        let card = user.score_with(&WeightedScorer::from_config(&config));
+        assert_eq!(card.factors.len(), 2);
+        assert_eq!(card.total, 75.0);
+        assert_eq!(user.score, 75.0);
+
+        // 同样的胜率，盈利更稳定的钱包默认评分更高
+        let steady = round_trips(&[(3_600, 1_500_000_000), (3_600, 1_500_000_000)]);
+        let lucky = round_trips(&[(5, 5_000_000_000), (5, 100_000_000)]);
+        let scorer = WeightedScorer::default();
+        assert!(scorer.score(&steady).total > scorer.score(&lucky).total);
+    }
+}
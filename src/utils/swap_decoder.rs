@@ -0,0 +1,203 @@
+use solana_sdk::{pubkey::Pubkey, transaction::VersionedTransaction};
+use solana_transaction_status_client_types::UiTransactionStatusMeta;
+use std::collections::HashMap;
+
+use crate::constant::WSOL_MINT_PUBKEY;
+use crate::states::swap_event::{SwapEvent, TokenAmount, TradeSide, Venue};
+use crate::utils::analyze_utils::{get_balance_change, is_target, SolChange, TokenAmountChange};
+use crate::utils::instruction_walker::{
+    find_routed_swaps, walk_instructions, DetectedSwap, RoutedSwap, WalkedInstruction,
+};
+use crate::utils::pump_parser::PumpEvent;
+
+// pump.fun 代币与 sol 的精度
+pub const PUMP_TOKEN_DECIMALS: u8 = 6;
+pub const SOL_DECIMALS: u8 = 9;
+
+/// 一笔已经解析好完整账户列表与指令的交易，所有下游的解析都基于它
+#[derive(Debug, Clone)]
+pub struct DecodedTransaction {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub account_keys: Vec<Pubkey>, // 完整的账户列表(静态账户 + 地址查找表加载的账户)
+    pub instructions: Vec<WalkedInstruction>, // 展开后的指令(顶层指令 + 内部指令)
+    pub num_signatures: u8,
+    pub meta: Option<UiTransactionStatusMeta>,
+}
+
+impl DecodedTransaction {
+    pub fn new(
+        signature: String,
+        slot: u64,
+        block_time: Option<i64>,
+        tx: &VersionedTransaction,
+        account_keys: Vec<Pubkey>,
+        meta: Option<UiTransactionStatusMeta>,
+    ) -> Self {
+        let instructions =
+            walk_instructions(&account_keys, tx.message.instructions(), meta.as_ref());
+        Self {
+            signature,
+            slot,
+            block_time,
+            account_keys,
+            instructions,
+            num_signatures: tx.message.header().num_required_signatures,
+            meta,
+        }
+    }
+
+    /// 是否为买卖代币的交易
+    pub fn is_target(&self) -> bool {
+        is_target(&self.instructions)
+    }
+
+    /// 交易中所有的 swap 及其调用路径
+    pub fn routed_swaps(&self) -> Vec<RoutedSwap> {
+        find_routed_swaps(&self.account_keys, &self.instructions, self.meta.as_ref())
+    }
+
+    /// 钱包在这笔交易中的 sol 与代币余额变化，没有 meta 时返回 None
+    pub fn balance_change(
+        &self,
+        wallet: &Pubkey,
+        token_addresses: &[String],
+    ) -> Option<(SolChange, HashMap<String, TokenAmountChange>)> {
+        get_balance_change(
+            wallet,
+            &self.account_keys,
+            &self.instructions,
+            self.num_signatures,
+            token_addresses,
+            self.meta.as_ref()?,
+        )
+        .ok()
+    }
+
+    /// 把交易中属于 `wallet` 的 swap 转换成 SwapEvent
+    /// - 成交数量优先使用钱包余额的真实变化，交易中只有一个 swap 时 sol 数量使用 swap 本身的 sol 收支
+    /// - 拿不到余额变化时退回到指令中的数量(买入的 max_sol_cost / 卖出的 min_sol_output 只是上下限)，
+    ///   这样的事件标记为 `estimated`
+    pub fn swap_events(&self, wallet: &Pubkey) -> Vec<SwapEvent> {
+        let swaps: Vec<RoutedSwap> = self
+            .routed_swaps()
+            .into_iter()
+            .filter(|swap| swap.wallet == *wallet)
+            .collect();
+        let balance_change = self.balance_change(wallet, &[]);
+        let single_swap = swaps.len() == 1;
+
+        swaps
+            .iter()
+            .filter_map(|routed| {
+                let (mint, side, token_amount, quote_amount, venue, estimated) = match &routed.swap
+                {
+                    DetectedSwap::Pump(event) => {
+                        let (side, mint, instruction_amount, sol_limit) = match event {
+                            PumpEvent::Buy {
+                                mint,
+                                amount,
+                                max_sol_cost,
+                                ..
+                            } => (TradeSide::Buy, *mint, *amount, *max_sol_cost),
+                            PumpEvent::Sell {
+                                mint,
+                                amount,
+                                min_sol_output,
+                                ..
+                            } => (TradeSide::Sell, *mint, *amount, *min_sol_output),
+                            PumpEvent::Create { .. } => return None,
+                        };
+                        let (token_amount, sol_amount) =
+                            resolve_amounts(side, &mint, balance_change.as_ref(), single_swap);
+                        (
+                            mint,
+                            side,
+                            token_amount.unwrap_or(TokenAmount::new(
+                                instruction_amount as u128,
+                                PUMP_TOKEN_DECIMALS,
+                            )),
+                            TokenAmount::new(sol_amount.unwrap_or(sol_limit) as u128, SOL_DECIMALS),
+                            Venue::PumpFun,
+                            sol_amount.is_none(),
+                        )
+                    }
+                    DetectedSwap::Raydium(swap) => {
+                        // 只统计与 sol 之间的买卖
+                        let (side, mint, token_amount, token_decimals, sol_amount) =
+                            if swap.input_mint == WSOL_MINT_PUBKEY {
+                                (
+                                    TradeSide::Buy,
+                                    swap.output_mint,
+                                    swap.amount_out,
+                                    swap.output_decimals,
+                                    swap.amount_in,
+                                )
+                            } else if swap.output_mint == WSOL_MINT_PUBKEY {
+                                (
+                                    TradeSide::Sell,
+                                    swap.input_mint,
+                                    swap.amount_in,
+                                    swap.input_decimals,
+                                    swap.amount_out,
+                                )
+                            } else {
+                                return None;
+                            };
+                        (
+                            mint,
+                            side,
+                            TokenAmount::new(token_amount as u128, token_decimals),
+                            TokenAmount::new(sol_amount as u128, SOL_DECIMALS),
+                            Venue::RaydiumAmmV4,
+                            false,
+                        )
+                    }
+                };
+
+                Some(SwapEvent {
+                    wallet: *wallet,
+                    mint,
+                    side,
+                    price: SwapEvent::compute_price(&token_amount, &quote_amount),
+                    token_amount,
+                    quote_amount,
+                    quote_mint: WSOL_MINT_PUBKEY,
+                    slot: Some(self.slot),
+                    block_time: self.block_time.unwrap_or(0).max(0) as u64,
+                    venue,
+                    signature: self.signature.clone(),
+                    route: routed.route.clone(),
+                    value_usd: None,
+                    cost_basis_usd: None,
+                    estimated,
+                })
+            })
+            .collect()
+    }
+}
+
+// 从余额变化中得到钱包真实的代币数量与 sol 数量
+fn resolve_amounts(
+    side: TradeSide,
+    mint: &Pubkey,
+    balance_change: Option<&(SolChange, HashMap<String, TokenAmountChange>)>,
+    single_swap: bool,
+) -> (Option<TokenAmount>, Option<u64>) {
+    let Some((sol_change, token_changes)) = balance_change else {
+        return (None, None);
+    };
+    // 买入时代币增加、sol 减少，卖出时相反
+    let sign: i128 = match side {
+        TradeSide::Buy => 1,
+        TradeSide::Sell => -1,
+    };
+    let token_amount = token_changes
+        .get(&mint.to_string())
+        .filter(|change| change.amount * sign > 0)
+        .map(|change| TokenAmount::new((change.amount * sign) as u128, change.decimals));
+    let sol_amount = (single_swap && (sol_change.swap_notional as i128) * sign < 0)
+        .then(|| sol_change.swap_notional.unsigned_abs());
+    (token_amount, sol_amount)
+}